@@ -0,0 +1,5123 @@
+use std::{thread, time};
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::io::Read;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+fn odd(value:isize) -> bool {
+    value&1 == 1
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+    End,
+    Null,
+    Wall,
+}
+impl Direction {
+    pub const MOVES: [Direction; 4] = [Direction::Left, Direction::Right, Direction::Up, Direction::Down];
+
+    pub fn invert(&self) -> Direction {
+        match self {
+            Direction::Left  => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up    => Direction::Down,
+            Direction::Down  => Direction::Up,
+            Direction::End   => Direction::End,
+            Direction::Null  => Direction::Null,
+            Direction::Wall  => Direction::Wall,
+        }
+    }
+    /* A step the head can take. On the field: a body segment pointing
+     * on towards the tail. */
+    pub fn is_movement(&self) -> bool {
+        matches!(self, Direction::Left | Direction::Right | Direction::Up | Direction::Down)
+    }
+    /* The last segment of the snake, not to be confused with is_empty() */
+    pub fn is_tail(&self) -> bool {
+        *self == Direction::End
+    }
+    /* A free cell */
+    pub fn is_empty(&self) -> bool {
+        *self == Direction::Null
+    }
+    /* Any segment of the snake, tail included */
+    pub fn is_snake(&self) -> bool {
+        self.is_movement() || self.is_tail()
+    }
+    /* Single letter used in replays */
+    pub fn to_char(self) -> char {
+        match self {
+            Direction::Left  => 'L',
+            Direction::Right => 'R',
+            Direction::Up    => 'U',
+            Direction::Down  => 'D',
+            Direction::End   => 'E',
+            Direction::Null  => 'N',
+            Direction::Wall  => 'W',
+        }
+    }
+    pub fn from_char(c:char) -> Option<Direction> {
+        match c {
+            'L' => Some(Direction::Left),
+            'R' => Some(Direction::Right),
+            'U' => Some(Direction::Up),
+            'D' => Some(Direction::Down),
+            'E' => Some(Direction::End),
+            'N' => Some(Direction::Null),
+            'W' => Some(Direction::Wall),
+            _   => None,
+        }
+    }
+    pub fn random(rng:&mut StdRng) -> Direction {
+        match rng.gen_range(0..4) {
+            0 => Direction::Left,
+            1 => Direction::Right,
+            2 => Direction::Up,
+            3 => Direction::Down,
+            _ => panic!("you can't even get modulo to work dork!"),
+        }
+    }
+}
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Direction::Left  => write!(f, "🡸"),
+            Direction::Right => write!(f, "🡺"),
+            Direction::Up    => write!(f, "🡹"),
+            Direction::Down  => write!(f, "🡻"),
+            Direction::End   => write!(f, "•"),
+            Direction::Null  => write!(f, " "),
+            Direction::Wall  => write!(f, "█"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Coordinate {
+    pub x: isize,
+    pub y: isize,
+}
+impl Coordinate {
+    pub fn move_towards(&self, dir:Direction) -> Coordinate {
+        match dir {
+            Direction::Left  => Coordinate{x:self.x-1, y:self.y},
+            Direction::Right => Coordinate{x:self.x+1, y:self.y},
+            Direction::Up    => Coordinate{x:self.x,   y:self.y-1},
+            Direction::Down  => Coordinate{x:self.x,   y:self.y+1},
+            Direction::End   => Coordinate{x:self.x,   y:self.y},
+            Direction::Null  => Coordinate{x:self.x,   y:self.y},
+            Direction::Wall  => Coordinate{x:self.x,   y:self.y},
+        }
+    }
+    pub fn random(&self, rng:&mut StdRng) -> Coordinate {
+        let x = rng.gen_range(0..self.x);
+        let y = rng.gen_range(0..self.y);
+        Coordinate{x, y}
+    }
+    /* The nearest coordinate on a board of the given dimension. Unlike
+     * wrapping, anything past an edge ends up on that edge. A board
+     * without cells has no nearest coordinate. */
+    pub fn clamp(&self, dimension:Coordinate) -> Option<Coordinate> {
+        if dimension.x <= 0 || dimension.y <= 0 {
+            return None;
+        }
+        Some(Coordinate{x: self.x.clamp(0, dimension.x-1), y: self.y.clamp(0, dimension.y-1)})
+    }
+    pub fn neighbors(&self) -> [Coordinate; 4] {
+        Direction::MOVES.map(|dir| self.move_towards(dir))
+    }
+    pub fn manhattan_distance(&self, other:Coordinate) -> isize {
+        let delta = self.difference(other);
+        delta.x.abs() + delta.y.abs()
+    }
+    /* The direction leading to an adjacent coordinate */
+    pub fn direction_to(&self, other:Coordinate) -> Option<Direction> {
+        Direction::MOVES.into_iter().find(|dir| self.move_towards(*dir) == other)
+    }
+    pub fn difference(&self, other:Coordinate) -> Coordinate {
+        Coordinate{x:other.x-self.x, y:other.y-self.y}
+    }
+}
+impl std::fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "({},{})", self.x, self.y)
+    }
+}
+
+/* Grid accesses made by the game and the snakes. Only counted when
+ * built with the "perf" feature, all zero otherwise. */
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct PerfCounters {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/* What happens when the snake runs off one side of the board */
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EdgeMode {
+    Wall,
+    /* come back in on the opposite side */
+    Wrap,
+}
+
+/* Wrapping only top and bottom, or only left and right, gives a
+ * cylinder. Wrapping all four a torus. */
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct EdgeModes {
+    pub top: EdgeMode,
+    pub bottom: EdgeMode,
+    pub left: EdgeMode,
+    pub right: EdgeMode,
+}
+impl Default for EdgeModes {
+    fn default() -> EdgeModes {
+        EdgeModes{top: EdgeMode::Wall, bottom: EdgeMode::Wall, left: EdgeMode::Wall, right: EdgeMode::Wall}
+    }
+}
+
+/*
+ * a Field is just a grid of directions
+ */
+#[derive(Clone)]
+pub struct Field {
+    dimension: Coordinate,
+    directions: Vec<Vec<Direction>>,
+    edges: EdgeModes,
+    /* Pairs of cells linked to each other: stepping onto one comes out
+     * on the other, one more step along. The cells themselves hold a
+     * wall so nothing ever stops on them. */
+    portals: Vec<(Coordinate, Coordinate)>,
+    /* kept up to date by set_direction_at() */
+    walls: usize,
+    /* Ghost mode: segments a newer one passed over, which the grid no
+     * longer shows, in the order they were covered. The chain visits a
+     * cell once per segment on it, newest first, so it reads the grid
+     * the first time and these, newest first, after that. */
+    buried: Vec<(Coordinate, Direction)>,
+    #[cfg(feature = "perf")]
+    perf: std::cell::Cell<PerfCounters>,
+}
+impl Field {
+    pub fn with_size(width:usize, height:usize) -> Result<Field, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("a {}x{} field has no cells", width, height));
+        }
+        Ok(Field::init(Coordinate{x:width as isize, y:height as isize}))
+    }
+    pub fn init(dimension: Coordinate) -> Field {
+        Field{
+            dimension,
+            directions: vec![vec![Direction::Null; dimension.x as usize]; dimension.y as usize],
+            edges: EdgeModes::default(),
+            portals: Vec::new(),
+            walls: 0,
+            buried: Vec::new(),
+            #[cfg(feature = "perf")]
+            perf: std::cell::Cell::new(PerfCounters::default()),
+        }
+    }
+    pub fn dimension(&self) -> Coordinate {
+        self.dimension
+    }
+    pub fn edges(&self) -> EdgeModes {
+        self.edges
+    }
+    pub fn get_direction_at(&self, position:Coordinate) -> Direction {
+        #[cfg(feature = "perf")]
+        self.perf.set(PerfCounters{reads: self.perf.get().reads+1, ..self.perf.get()});
+        self.directions[position.y as usize][position.x as usize]
+    }
+    pub fn set_direction_at(&mut self, position:Coordinate, direction:Direction) {
+        #[cfg(feature = "perf")]
+        self.perf.set(PerfCounters{writes: self.perf.get().writes+1, ..self.perf.get()});
+        let cell = &mut self.directions[position.y as usize][position.x as usize];
+        self.walls = self.walls + (direction == Direction::Wall) as usize - (*cell == Direction::Wall) as usize;
+        *cell = direction;
+    }
+    /* Cells holding a wall, portals included */
+    pub fn wall_count(&self) -> usize {
+        self.walls
+    }
+    pub fn next(&self, position:Coordinate) -> Coordinate {
+        self.next_towards(position, self.get_direction_at(position))
+    }
+    fn next_towards(&self, position:Coordinate, direction:Direction) -> Coordinate {
+        /* The body only crosses an edge where the head wrapped, but the
+         * link back points out the opposite edge, which need not wrap
+         * itself. So always wrap here. */
+        let wrap = |pos:Coordinate| Coordinate{x: pos.x.rem_euclid(self.dimension.x), y: pos.y.rem_euclid(self.dimension.y)};
+        let pos = wrap(position.move_towards(direction));
+        match self.portal_exit(pos) {
+            Some(exit) => wrap(exit.move_towards(direction)),
+            None       => pos,
+        }
+    }
+    /* Like Coordinate::move_towards() but wraps around the edges that are
+     * configured to and goes through portals. Off a walled edge the
+     * result is out of bounds. */
+    pub fn move_towards(&self, position:Coordinate, dir:Direction) -> Coordinate {
+        let pos = self.step_over_edge(position, dir);
+        match self.portal_exit(pos) {
+            Some(exit) => self.step_over_edge(exit, dir),
+            None       => pos,
+        }
+    }
+    /* The other end of the portal at position, if there is one */
+    pub fn portal_exit(&self, position:Coordinate) -> Option<Coordinate> {
+        self.portals.iter().find_map(|&(a, b)| {
+            if a == position { Some(b) } else if b == position { Some(a) } else { None }
+        })
+    }
+    pub fn portals(&self) -> &[(Coordinate, Coordinate)] {
+        &self.portals
+    }
+    /* Link two free cells. Portals may not touch each other, so stepping
+     * out of one never lands in the next. */
+    pub fn add_portal(&mut self, a:Coordinate, b:Coordinate) -> Result<(), String> {
+        for pos in [a, b] {
+            if !self.coordinate_in_bounds(pos) || !self.free_at(pos) {
+                return Err(format!("{} is not a free cell", pos));
+            }
+            let ends = self.portals.iter().flat_map(|&(a, b)| [a, b]);
+            if ends.chain([a, b]).any(|end| end.manhattan_distance(pos) == 1) {
+                return Err(format!("{} is next to another portal", pos));
+            }
+        }
+        if a == b {
+            return Err(format!("a portal from {} to itself", a));
+        }
+        self.set_direction_at(a, Direction::Wall);
+        self.set_direction_at(b, Direction::Wall);
+        self.portals.push((a, b));
+        Ok(())
+    }
+    fn step_over_edge(&self, position:Coordinate, dir:Direction) -> Coordinate {
+        let mut pos = position.move_towards(dir);
+        if pos.x < 0 && self.edges.left == EdgeMode::Wrap {
+            pos.x = self.dimension.x-1;
+        } else if pos.x >= self.dimension.x && self.edges.right == EdgeMode::Wrap {
+            pos.x = 0;
+        }
+        if pos.y < 0 && self.edges.top == EdgeMode::Wrap {
+            pos.y = self.dimension.y-1;
+        } else if pos.y >= self.dimension.y && self.edges.bottom == EdgeMode::Wrap {
+            pos.y = 0;
+        }
+        pos
+    }
+    /* Row by row number of a cell, 0 for the top left one */
+    pub fn index_of(&self, position:Coordinate) -> usize {
+        (position.y * self.dimension.x + position.x) as usize
+    }
+    pub fn coord_of(&self, index:usize) -> Coordinate {
+        let index = index as isize;
+        Coordinate{x: index % self.dimension.x, y: index / self.dimension.x}
+    }
+    pub fn coordinate_in_bounds(&self, position:Coordinate) -> bool {
+        position.x >= 0 && position.y >= 0 && position.x < self.dimension.x && position.y < self.dimension.y
+    }
+    /* On the outermost row or column */
+    pub fn is_border(&self, position:Coordinate) -> bool {
+        position.x == 0 || position.y == 0 || position.x == self.dimension.x-1 || position.y == self.dimension.y-1
+    }
+    pub fn free_at(&self, position:Coordinate) -> bool {
+        self.get_direction_at(position).is_empty()
+    }
+    pub fn perf_counters(&self) -> PerfCounters {
+        #[cfg(feature = "perf")]
+        return self.perf.get();
+        #[cfg(not(feature = "perf"))]
+        PerfCounters::default()
+    }
+    pub fn free_cells(&self) -> impl Iterator<Item = Coordinate> + '_ {
+        (0..self.dimension.y).flat_map(move |y| (0..self.dimension.x).map(move |x| Coordinate{x, y}))
+            .filter(|pos| self.free_at(*pos))
+    }
+    pub fn random_available(&self, rng:&mut StdRng) -> Option<Coordinate> {
+        let w = self.dimension.x;
+        let h = self.dimension.y;
+        let r = self.dimension.random(rng);
+
+        for y in 0..h {
+            for x in 0..w {
+                let p = Coordinate{x: (x+r.x)%w, y: (y+r.y)%h};
+                if self.free_at(p) {
+                    return Some(p);
+                }
+            }
+        }
+        None
+    }
+    /* How many of the cells a step away from pos are free, a cheap
+     * measure of how open the space around it is */
+    pub fn free_neighbor_count(&self, pos:Coordinate) -> usize {
+        Direction::MOVES.iter().map(|dir| self.move_towards(pos, *dir))
+            .filter(|next| self.coordinate_in_bounds(*next) && self.free_at(*next))
+            .count()
+    }
+    /* The free cell closest to from, counting steps over the grid
+     * regardless of what is in the way */
+    pub fn nearest_free(&self, from:Coordinate) -> Option<Coordinate> {
+        if self.free_at(from) {
+            return Some(from);
+        }
+        let mut seen = vec![vec![false; self.dimension.x as usize]; self.dimension.y as usize];
+        let mut queue = VecDeque::from([from]);
+        seen[from.y as usize][from.x as usize] = true;
+        while let Some(pos) = queue.pop_front() {
+            for next in pos.neighbors() {
+                if !self.coordinate_in_bounds(next) || seen[next.y as usize][next.x as usize] {
+                    continue;
+                }
+                if self.free_at(next) {
+                    return Some(next);
+                }
+                seen[next.y as usize][next.x as usize] = true;
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+    /* Steps from from to every cell, walking over free cells only. None
+     * for cells that are occupied or can't be reached. */
+    pub fn distance_map(&self, from:Coordinate) -> Vec<Vec<Option<usize>>> {
+        let mut distances = vec![vec![None; self.dimension.x as usize]; self.dimension.y as usize];
+        let mut queue = VecDeque::from([from]);
+        distances[from.y as usize][from.x as usize] = Some(0);
+        while let Some(pos) = queue.pop_front() {
+            let distance = distances[pos.y as usize][pos.x as usize].unwrap_or(0);
+            for dir in Direction::MOVES {
+                let next = self.move_towards(pos, dir);
+                if !self.coordinate_in_bounds(next) || !self.free_at(next) || distances[next.y as usize][next.x as usize].is_some() {
+                    continue;
+                }
+                distances[next.y as usize][next.x as usize] = Some(distance+1);
+                queue.push_back(next);
+            }
+        }
+        distances
+    }
+    /* Free cells that can be reached from from, not counting from itself */
+    pub fn reachable_count(&self, from:Coordinate) -> usize {
+        self.distance_map(from).iter().flatten().filter(|distance| distance.is_some()).count() - 1
+    }
+    /* An estimate of the longest path over free cells starting next to
+     * from, in steps. It walks one path only, always on to the free cell
+     * with the fewest ways out, so the real longest path may well be
+     * longer; but it never is shorter. Cheap enough to rank moves with,
+     * and a pocket the flood fill finds roomy but that can't be walked
+     * through in one go scores low. */
+    pub fn longest_open_run(&self, from:Coordinate) -> usize {
+        let mut visited = vec![vec![false; self.dimension.x as usize]; self.dimension.y as usize];
+        let exits = |pos:Coordinate, visited:&[Vec<bool>]| -> Vec<Coordinate> {
+            Direction::MOVES.iter().map(|dir| self.move_towards(pos, *dir))
+                .filter(|next| self.coordinate_in_bounds(*next) && self.free_at(*next) && !visited[next.y as usize][next.x as usize])
+                .collect()
+        };
+        visited[from.y as usize][from.x as usize] = true;
+        let mut pos = from;
+        let mut run = 0;
+        loop {
+            let next = exits(pos, &visited).into_iter().min_by_key(|next| exits(*next, &visited).len());
+            let Some(next) = next else {
+                return run;
+            };
+            visited[next.y as usize][next.x as usize] = true;
+            pos = next;
+            run += 1;
+        }
+    }
+    /* Number of separate areas the free cells fall apart in */
+    pub fn free_regions(&self) -> usize {
+        let mut seen = vec![vec![false; self.dimension.x as usize]; self.dimension.y as usize];
+        let mut regions = 0;
+        for start in self.free_cells() {
+            if seen[start.y as usize][start.x as usize] {
+                continue;
+            }
+            regions += 1;
+            seen[start.y as usize][start.x as usize] = true;
+            let mut queue = VecDeque::from([start]);
+            while let Some(pos) = queue.pop_front() {
+                for dir in Direction::MOVES {
+                    let next = self.move_towards(pos, dir);
+                    if !self.coordinate_in_bounds(next) || !self.free_at(next) || seen[next.y as usize][next.x as usize] {
+                        continue;
+                    }
+                    seen[next.y as usize][next.x as usize] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        regions
+    }
+    /* Turn about density of all cells into walls, picked at random from
+     * the free ones, skipping those that would cut the board in two.
+     * Returns how many were placed. */
+    pub fn generate_walls(&mut self, rng:&mut StdRng, density:f32) -> usize {
+        let target = (density.clamp(0.0, 1.0) * (self.dimension.x * self.dimension.y) as f32).round() as usize;
+        let mut candidates: Vec<Coordinate> = self.free_cells().collect();
+        candidates.shuffle(rng);
+        let mut placed = 0;
+        for pos in candidates {
+            if placed == target {
+                break;
+            }
+            self.set_direction_at(pos, Direction::Wall);
+            if self.is_connected() {
+                placed += 1;
+            } else {
+                self.set_direction_at(pos, Direction::Null);
+            }
+        }
+        placed
+    }
+    /* Can every cell that isn't a wall be reached from every other one,
+     * going over the snake as if it wasn't there? */
+    pub fn is_connected(&self) -> bool {
+        let cells = (0..self.dimension.y).flat_map(|y| (0..self.dimension.x).map(move |x| Coordinate{x, y}));
+        let open: Vec<Coordinate> = cells.filter(|pos| self.get_direction_at(*pos) != Direction::Wall).collect();
+        let Some(&start) = open.first() else {
+            return true;
+        };
+        let mut seen = vec![vec![false; self.dimension.x as usize]; self.dimension.y as usize];
+        seen[start.y as usize][start.x as usize] = true;
+        let mut queue = VecDeque::from([start]);
+        let mut reached = 1;
+        while let Some(pos) = queue.pop_front() {
+            for dir in Direction::MOVES {
+                let next = self.move_towards(pos, dir);
+                if !self.coordinate_in_bounds(next) || self.get_direction_at(next) == Direction::Wall || seen[next.y as usize][next.x as usize] {
+                    continue;
+                }
+                seen[next.y as usize][next.x as usize] = true;
+                reached += 1;
+                queue.push_back(next);
+            }
+        }
+        reached == open.len()
+    }
+    /* Follow chain backwards. Drop last segment, return its coordinates */
+    pub fn drop_last_in_chain(&mut self, start:Coordinate) -> Coordinate {
+        if !self.buried.is_empty() {
+            return self.drop_last_buried(start);
+        }
+        let (b, a) = self.find_last(start);
+        self.set_direction_at(a, Direction::End);
+        self.set_direction_at(b, Direction::Null);
+        b
+    }
+    /* drop_last_in_chain() for a snake lying over itself. The tail is
+     * the oldest segment on its cell, so buried deepest if at all. */
+    fn drop_last_buried(&mut self, start:Coordinate) -> Coordinate {
+        let links: Vec<(Coordinate, usize)> = self.chain_links(start).collect();
+        let [.., (a, a_visit), (b, _)] = links[..] else {
+            return start;
+        };
+        match a_visit {
+            0     => self.set_direction_at(a, Direction::End),
+            visit => {
+                let layer = self.buried_at(a).nth_back(visit - 1).expect("the chain went through it");
+                self.buried[layer].1 = Direction::End;
+            }
+        }
+        let deepest = self.buried_at(b).next();
+        match deepest {
+            Some(layer) => { self.buried.remove(layer); }
+            None        => self.set_direction_at(b, Direction::Null),
+        }
+        b
+    }
+    /* Ghost mode: a segment is about to be laid over the one on position */
+    pub fn bury(&mut self, position:Coordinate) {
+        self.buried.push((position, self.get_direction_at(position)));
+    }
+    /* Segments covered by others, see bury() */
+    pub fn buried_count(&self) -> usize {
+        self.buried.len()
+    }
+    /* Indices into buried of the segments under position, oldest first */
+    fn buried_at(&self, position:Coordinate) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.buried.iter().enumerate().filter(move |(_, (pos, _))| *pos == position).map(|(i, _)| i)
+    }
+    /* Every cell of the chain from start and how many times the chain
+     * was on it before, to know which of the segments there it is */
+    fn chain_links(&self, start:Coordinate) -> impl Iterator<Item = (Coordinate, usize)> + '_ {
+        let mut visits: Vec<Coordinate> = Vec::new();
+        let mut next = Some(start);
+        std::iter::from_fn(move || {
+            let pos = next?;
+            let visit = visits.iter().filter(|seen| **seen == pos).count();
+            let dir = match visit {
+                0     => self.get_direction_at(pos),
+                visit => self.buried_at(pos).nth_back(visit - 1).map_or(Direction::End, |layer| self.buried[layer].1),
+            };
+            if !self.buried.is_empty() {
+                visits.push(pos);
+            }
+            next = (!dir.is_tail()).then(|| self.next_towards(pos, dir));
+            Some((pos, visit))
+        })
+    }
+    /* Lay down a snake given its segments from head to tail */
+    pub fn place_body(&mut self, segments:&[Coordinate]) -> Result<(), String> {
+        let Some(&tail) = segments.last() else {
+            return Err("a body needs at least one segment".to_string());
+        };
+        for (i, segment) in segments.iter().enumerate() {
+            if !self.coordinate_in_bounds(*segment) {
+                return Err(format!("segment {} is out of bounds", segment));
+            }
+            if segments[..i].contains(segment) {
+                return Err(format!("segment {} is used twice", segment));
+            }
+        }
+        for pair in segments.windows(2) {
+            match pair[0].direction_to(pair[1]) {
+                Some(dir) => self.set_direction_at(pair[0], dir),
+                None => return Err(format!("segments {} and {} are not adjacent", pair[0], pair[1])),
+            }
+        }
+        self.set_direction_at(tail, Direction::End);
+        Ok(())
+    }
+    /* Follow the directions from start, yielding every coordinate up to
+     * and including the tail */
+    pub fn iter_chain(&self, start:Coordinate) -> impl Iterator<Item = Coordinate> + '_ {
+        self.chain_links(start).map(|(pos, _)| pos)
+    }
+    /* All segments from start up to and including the tail */
+    pub fn body(&self, start:Coordinate) -> Vec<Coordinate> {
+        self.iter_chain(start).collect()
+    }
+    /* The tail and the segment right before it */
+    pub fn find_last(&self, start:Coordinate) -> (Coordinate, Coordinate) {
+        let mut last = (start, start);
+        for pos in self.iter_chain(start).skip(1) {
+            last = (pos, last.0);
+        }
+        last
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum BorderStyle {
+    None,
+    Single,
+    Double,
+    Heavy,
+    Ascii,
+}
+impl BorderStyle {
+    /* top left, top right, bottom left, bottom right, horizontal, vertical */
+    pub fn glyphs(&self) -> [&'static str; 6] {
+        match self {
+            BorderStyle::None   => [" ", " ", " ", " ", " ", " "],
+            BorderStyle::Single => ["┌", "┐", "└", "┘", "─", "│"],
+            BorderStyle::Double => ["╔", "╗", "╚", "╝", "═", "║"],
+            BorderStyle::Heavy  => ["┏", "┓", "┗", "┛", "━", "┃"],
+            BorderStyle::Ascii  => ["+", "+", "+", "+", "-", "|"],
+        }
+    }
+    /* horizontal, vertical; dashed for the edges that wrap */
+    pub fn seam_glyphs(&self) -> [&'static str; 2] {
+        match self {
+            BorderStyle::None   => [" ", " "],
+            BorderStyle::Single => ["┄", "┆"],
+            BorderStyle::Double => ["╌", "╎"],
+            BorderStyle::Heavy  => ["┅", "┇"],
+            BorderStyle::Ascii  => [".", ":"],
+        }
+    }
+}
+
+/* Everything that changes how the game looks but not how it plays */
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub border: BorderStyle,
+    /* characters per cell, 1 to 3. Narrow cells fit bigger boards. */
+    pub cell_width: usize,
+    /* draw which way each body segment goes, or just blocks */
+    pub show_body_arrows: bool,
+    /* faint arrows on the free cells along the cycle the snake follows,
+     * if it follows one */
+    pub show_cycle: bool,
+    /* colour the free cells by how many steps the head is away, from red
+     * close by to green far off, and cross out the ones it can't reach */
+    pub show_heatmap: bool,
+    /* cells in the HUD bar showing how close the board is to full,
+     * 0 hides it */
+    pub progress_bar_width: usize,
+    /* cells of room to the left of and above the board, to fit it into
+     * a bigger frame or image */
+    pub origin: (usize, usize),
+    /* Draw only this many cells across and down, following the head,
+     * for boards too big for the terminal */
+    pub viewport: Option<(usize, usize)>,
+}
+impl Theme {
+    /* cell_width brought within 1 to 3, what cells are really drawn at */
+    pub fn cell_width(&self) -> usize {
+        self.cell_width.clamp(1, 3)
+    }
+    pub fn cell(&self, glyph:impl std::fmt::Display) -> String {
+        match self.cell_width() {
+            1 => format!("{}", glyph),
+            2 => format!("{} ", glyph),
+            _ => format!(" {} ", glyph),
+        }
+    }
+}
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme{
+            border: BorderStyle::Heavy,
+            cell_width: 3,
+            show_body_arrows: true,
+            show_cycle: false,
+            show_heatmap: false,
+            progress_bar_width: 20,
+            origin: (0, 0),
+            viewport: None,
+        }
+    }
+}
+
+/* Passes writes on, putting the indent in front of every line */
+struct Indented<'a, W:std::io::Write> {
+    out: &'a mut W,
+    indent: &'a [u8],
+    line_start: bool,
+}
+impl<W:std::io::Write> std::io::Write for Indented<'_, W> {
+    fn write(&mut self, buf:&[u8]) -> std::io::Result<usize> {
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if self.line_start {
+                self.out.write_all(self.indent)?;
+            }
+            self.out.write_all(line)?;
+            self.line_start = line.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/* The snake starts with one segment and wins when it fills the board.
+ * An empty board has room for no apples at all. */
+pub fn max_theoretical_apples(width:usize, height:usize) -> u32 {
+    (width.saturating_mul(height) as u32).saturating_sub(1)
+}
+
+/* Apples to keep out on a board so there is one for every
+ * cells_per_apple cells, and always at least one */
+pub fn apples_for_area(width:usize, height:usize, cells_per_apple:usize) -> usize {
+    (width * height / cells_per_apple.max(1)).max(1)
+}
+
+/* Where new apples go */
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ApplePlacement {
+    Uniform,
+    /* as far from the head as possible, the hard mode */
+    FarFromHead,
+    /* as close to the head as possible, the easy mode */
+    NearHead,
+    /* always the same cell, or a random one while that is taken */
+    Fixed(Coordinate),
+}
+
+/* Rules that change what eating an apple does */
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    /* the snake grows by one */
+    Classic,
+    /* the whole body turns into walls where it lies and the head
+     * starts over, one cell long, on a random free cell */
+    Teleport,
+    /* practice: the snake grows as usual but passes through itself */
+    Ghost,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum StepOutcome {
+    Moved,
+    AteApple,
+    Won,
+    Gibberish,
+    CrashedInWall,
+    AteSnake,
+}
+impl StepOutcome {
+    /* Did the snake actually move? */
+    pub fn committed(&self) -> bool {
+        matches!(self, StepOutcome::Moved | StepOutcome::AteApple | StepOutcome::Won)
+    }
+}
+
+/* What is on a cell, as seen from the game rather than the grid */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Cell {
+    Empty,
+    Head,
+    /* pointing to the next segment towards the tail */
+    Body(Direction),
+    Tail,
+    Apple,
+    Wall,
+    Portal,
+    OutOfBounds,
+}
+
+#[derive(Clone)]
+pub struct Game {
+    head: Coordinate,
+    apple: Coordinate,
+    field: Field,
+    apples: u32,
+    moves: u32,
+    length: usize,
+    last_direction: Option<Direction>,
+    last_apple: Option<Coordinate>,
+    /* walls that disappear after the given number of moves */
+    temporary_walls: Vec<(Coordinate, u32)>,
+    apple_placement: ApplePlacement,
+    mode: GameMode,
+    /* win at this length instead of only on a full board */
+    length_goal: Option<usize>,
+    /* puzzle levels: every apple in order, and how many have been put
+     * out. Overrides apple_placement while not empty. */
+    apple_sequence: Vec<Coordinate>,
+    next_in_sequence: usize,
+    /* demos: apples go round these cells over and over, skipping taken
+     * ones. Overrides apple_placement while not empty. */
+    apple_pattern: Vec<Coordinate>,
+    next_in_pattern: usize,
+    /* run check_invariants() after every step */
+    debug_invariants: bool,
+    seed: u64,
+    /* head and apple were handed to init_with_positions(), not drawn
+     * from the seed */
+    picked_start: bool,
+    /* every direction handed to step(), for replays */
+    history: Vec<Direction>,
+    /* committed moves per direction, in Direction::MOVES order */
+    direction_counts: [u32; 4],
+    rng: StdRng,
+    theme: Theme,
+    /* previous head positions, newest first. Purely cosmetic. */
+    trail: VecDeque<Coordinate>,
+    trail_length: usize,
+    /* for every cell the way on along the snake's cycle. Cosmetic too. */
+    cycle_overlay: Option<Vec<Vec<Direction>>>,
+    /* moves an apple stays before it despawns, None keeps it forever */
+    apple_lifetime: Option<u32>,
+    apple_age: u32,
+    missed_apples: u32,
+    /* score lost for every apple that despawned */
+    miss_penalty: u32,
+    /* when the current apple was put out and how far from the head, and
+     * over all eaten apples the sum of both, for path_efficiency() */
+    apple_placed_at: u32,
+    apple_placed_distance: u32,
+    moves_to_apples: u32,
+    distance_to_apples: u32,
+    /* easy mode: every this many moves the apple creeps a cell closer */
+    apple_magnet: Option<u32>,
+    /* apples out at once on big boards. apple is the one the magnet,
+     * lifetime and path_efficiency() are about, the others wait in
+     * more_apples and simply grow the snake when eaten. */
+    apple_count: usize,
+    more_apples: Vec<Coordinate>,
+}
+impl Game {
+    pub fn init(width: usize, height: usize) -> Game {
+        Game::init_seeded(width, height, 42)
+    }
+    pub fn init_seeded(width: usize, height: usize, seed: u64) -> Game {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let field_dimension = Coordinate{x:width as isize, y:height as isize};
+        let mut field = Field::init(field_dimension);
+        let head = field_dimension.random(&mut rng);
+        let direction = Direction::End;
+        field.set_direction_at(head, direction);
+        let apple_opt = field.random_available(&mut rng);
+        let apple = match apple_opt {
+            Some(apple) => apple,
+            None        => panic!("You goofed"),
+        };
+        Game::with_field(field, head, apple, seed, rng)
+    }
+    /* Start from a hand picked head and apple rather than random ones.
+     * Later apples are still placed at random, seeded like init(). */
+    pub fn init_with_positions(width:usize, height:usize, head:Coordinate, apple:Coordinate) -> Result<Game, String> {
+        let mut field = Field::with_size(width, height)?;
+        if !field.coordinate_in_bounds(head) {
+            return Err(format!("head {} is out of bounds", head));
+        }
+        if !field.coordinate_in_bounds(apple) {
+            return Err(format!("apple {} is out of bounds", apple));
+        }
+        if head == apple {
+            return Err(format!("head and apple are both on {}", head));
+        }
+        field.set_direction_at(head, Direction::End);
+        let mut game = Game::with_field(field, head, apple, 42, StdRng::seed_from_u64(42));
+        game.picked_start = true;
+        Ok(game)
+    }
+    fn with_field(field:Field, head:Coordinate, apple:Coordinate, seed:u64, rng:StdRng) -> Game {
+        Game{
+            head,
+            apple,
+            field,
+            apples: 0,
+            moves: 0,
+            length: 1,
+            last_direction: None,
+            last_apple: None,
+            temporary_walls: Vec::new(),
+            apple_placement: ApplePlacement::Uniform,
+            mode: GameMode::Classic,
+            length_goal: None,
+            apple_sequence: Vec::new(),
+            next_in_sequence: 0,
+            apple_pattern: Vec::new(),
+            next_in_pattern: 0,
+            debug_invariants: false,
+            seed,
+            picked_start: false,
+            history: Vec::new(),
+            direction_counts: [0; 4],
+            rng,
+            theme: Theme::default(),
+            trail: VecDeque::new(),
+            trail_length: 0,
+            cycle_overlay: None,
+            apple_lifetime: None,
+            apple_age: 0,
+            missed_apples: 0,
+            miss_penalty: 0,
+            apple_placed_at: 0,
+            apple_placed_distance: head.manhattan_distance(apple) as u32,
+            moves_to_apples: 0,
+            distance_to_apples: 0,
+            apple_magnet: None,
+            apple_count: 1,
+            more_apples: Vec::new(),
+        }
+    }
+    /* Set up a puzzle: the walls of the level, the head on its start and
+     * the first of its apples out, the next one only once that is eaten */
+    pub fn from_level(level:&Level) -> Result<Game, String> {
+        let Some(&first) = level.apples.first() else {
+            return Err("a level needs at least one apple".to_string());
+        };
+        let mut game = Game::init_with_positions(level.width, level.height, level.head, first)?;
+        for wall in &level.walls {
+            game.field.set_direction_at(*wall, Direction::Wall);
+        }
+        game.apple_sequence = level.apples.clone();
+        game.next_in_sequence = 1;
+        Ok(game)
+    }
+    /* Apples of the level still to come after the current one */
+    pub fn apples_left_in_sequence(&self) -> usize {
+        self.apple_sequence.len().saturating_sub(self.next_in_sequence)
+    }
+    pub fn from_config(config:&GameConfig) -> Result<Game, String> {
+        Field::with_size(config.width, config.height)?;
+        let mut game = Game::init_seeded(config.width, config.height, config.seed);
+        game.set_edges(config.edges);
+        if config.apples != ApplePlacement::Uniform {
+            game.set_apple_placement(config.apples);
+        }
+        game.theme = config.theme;
+        game.mode = config.mode;
+        game.length_goal = config.length_goal;
+        game.apple_magnet = config.apple_magnet;
+        if config.wall_density > 0.0 {
+            game.generate_walls(config.wall_density);
+        }
+        if let Some(cells) = config.cells_per_apple {
+            game.set_apple_count(apples_for_area(config.width, config.height, cells));
+        }
+        Ok(game)
+    }
+    /* Render the last `length` head positions fading out, 0 disables */
+    pub fn set_trail_length(&mut self, length: usize) {
+        self.trail_length = length;
+        self.trail.truncate(length);
+    }
+    /* Remember the cycle, cells in visiting order, to draw it when the
+     * theme asks for it */
+    pub fn set_cycle_overlay(&mut self, cycle:Option<&[Coordinate]>) {
+        self.cycle_overlay = cycle.map(|cycle| {
+            let dimension = self.field.dimension;
+            let mut overlay = vec![vec![Direction::Null; dimension.x as usize]; dimension.y as usize];
+            for (i, pos) in cycle.iter().enumerate() {
+                let next = cycle[(i+1) % cycle.len()];
+                overlay[pos.y as usize][pos.x as usize] = pos.direction_to(next).unwrap_or(Direction::Null);
+            }
+            overlay
+        });
+    }
+    /* Where the cycle goes on from pos, if there is one */
+    pub fn cycle_overlay_at(&self, pos:Coordinate) -> Option<Direction> {
+        let overlay = self.cycle_overlay.as_ref()?;
+        Some(overlay[pos.y as usize][pos.x as usize]).filter(|dir| dir.is_movement())
+    }
+    fn set_head(&mut self, head:Coordinate) {
+        if self.trail_length > 0 {
+            self.trail.push_front(self.head);
+            self.trail.truncate(self.trail_length);
+        }
+        self.head = head;
+    }
+    /* Last digit of the distance in a colour running from red to green
+     * over 0 to farthest, or a dim cross if the cell can't be reached */
+    fn heatmap_glyph(distance:Option<usize>, farthest:usize) -> String {
+        const RAMP: [u8; 8] = [196, 202, 208, 214, 220, 190, 118, 46];
+        match distance {
+            Some(distance) => format!("\x1b[38;5;{}m{}\x1b[0m", RAMP[distance * (RAMP.len() - 1) / farthest.max(1)], distance % 10),
+            None           => "\x1b[2m×\x1b[0m".to_string(),
+        }
+    }
+    fn trail_glyph(&self, pos:Coordinate) -> Option<&'static str> {
+        const FADE: [&str; 3] = ["▓", "▒", "░"];
+        let age = self.trail.iter().position(|&p| p == pos)?;
+        Some(FADE[age * FADE.len() / self.trail_length])
+    }
+    /* Also moves the current apple according to the new placement */
+    pub fn set_apple_placement(&mut self, placement:ApplePlacement) -> bool {
+        self.apple_placement = placement;
+        self.place_new_apple()
+    }
+    /* Also moves the current apple to the first cell of the pattern.
+     * An empty pattern goes back to apple_placement. */
+    pub fn set_apple_pattern(&mut self, pattern:&[Coordinate]) -> bool {
+        self.apple_pattern = pattern.to_vec();
+        self.next_in_pattern = 0;
+        self.place_new_apple()
+    }
+    fn next_in_apple_pattern(&mut self) -> Option<Coordinate> {
+        for _ in 0..self.apple_pattern.len() {
+            let pos = self.apple_pattern[self.next_in_pattern];
+            self.next_in_pattern = (self.next_in_pattern + 1) % self.apple_pattern.len();
+            if pos != self.head && self.field.coordinate_in_bounds(pos) && self.field.free_at(pos) {
+                return Some(pos);
+            }
+        }
+        None
+    }
+    fn place_new_apple(&mut self) -> bool {
+        let head = self.head;
+        if !self.apple_sequence.is_empty() {
+            let Some(&next) = self.apple_sequence.get(self.next_in_sequence) else {
+                return false;
+            };
+            self.next_in_sequence += 1;
+            /* should the body be in the way, as close as it gets */
+            let Some(apple) = self.field.nearest_free(next) else {
+                return false;
+            };
+            self.apple = apple;
+            self.apple_placed();
+            return true;
+        }
+        let apple_opt = match self.apple_placement {
+            _ if !self.apple_pattern.is_empty() => self.next_in_apple_pattern().or_else(|| self.field.random_available(&mut self.rng)),
+            ApplePlacement::Uniform     => self.field.random_available(&mut self.rng),
+            ApplePlacement::FarFromHead => self.field.free_cells().max_by_key(|pos| head.manhattan_distance(*pos)),
+            ApplePlacement::NearHead    => self.field.nearest_free(head),
+            ApplePlacement::Fixed(pos)  if self.field.coordinate_in_bounds(pos) && self.field.free_at(pos) => Some(pos),
+            ApplePlacement::Fixed(_)    => self.field.random_available(&mut self.rng),
+        };
+        /* An apple on the head would be eaten without a move, and one on
+         * the body can never be reached. Whatever the strategy came up
+         * with, settle for any free cell instead. */
+        let apple_opt = match apple_opt {
+            Some(pos) if pos == head || !self.field.free_at(pos) => self.field.random_available(&mut self.rng),
+            apple_opt => apple_opt,
+        };
+        /* Nor on top of another apple. With no cell left for it one of
+         * the others takes its place. */
+        let apple_opt = match apple_opt {
+            Some(pos) if self.more_apples.contains(&pos) => self.random_apple_cell().or_else(|| self.more_apples.pop()),
+            apple_opt => apple_opt,
+        };
+        self.apple = match apple_opt {
+            Some(apple) => apple,
+            None        => return false,
+        };
+        self.apple_placed();
+        true
+    }
+    /* Any free cell without an apple or the head on it */
+    fn random_apple_cell(&mut self) -> Option<Coordinate> {
+        let cells: Vec<Coordinate> = self.field.free_cells().filter(|pos| *pos != self.head && !self.is_apple(*pos)).collect();
+        cells.choose(&mut self.rng).copied()
+    }
+    /* Keep count apples on the board, as long as there is room for
+     * them. Returns how many there are. */
+    pub fn set_apple_count(&mut self, count:usize) -> usize {
+        self.apple_count = count.max(1);
+        self.more_apples.truncate(self.apple_count - 1);
+        self.top_up_apples();
+        self.more_apples.len() + 1
+    }
+    fn top_up_apples(&mut self) {
+        self.more_apples.retain(|pos| self.field.free_at(*pos));
+        while self.more_apples.len() + 1 < self.apple_count {
+            let Some(pos) = self.random_apple_cell() else {
+                break;
+            };
+            self.more_apples.push(pos);
+        }
+    }
+    /* Every apple on the board, apple() first */
+    pub fn apple_positions(&self) -> Vec<Coordinate> {
+        std::iter::once(self.apple).chain(self.more_apples.iter().copied()).collect()
+    }
+    pub fn is_apple(&self, pos:Coordinate) -> bool {
+        pos == self.apple || self.more_apples.contains(&pos)
+    }
+    fn apple_placed(&mut self) {
+        self.apple_age = 0;
+        self.apple_placed_at = self.moves;
+        self.apple_placed_distance = self.head.manhattan_distance(self.apple) as u32;
+    }
+    /* Moves taken to the apples eaten so far over the straight line
+     * distance from the head when each was put out. 1 is as good as it
+     * gets, twice as many moves as needed is 2. None before the first. */
+    pub fn path_efficiency(&self) -> Option<f32> {
+        (self.distance_to_apples > 0).then(|| self.moves_to_apples as f32 / self.distance_to_apples as f32)
+    }
+    /* Have the apple move a cell towards the head every interval moves,
+     * for beginners. None or 0 leaves it where it is. */
+    pub fn set_apple_magnet(&mut self, interval:Option<u32>) {
+        self.apple_magnet = interval;
+    }
+    fn pull_apple(&mut self) {
+        let Some(interval) = self.apple_magnet.filter(|interval| *interval > 0) else {
+            return;
+        };
+        if !self.moves.is_multiple_of(interval) {
+            return;
+        }
+        /* never onto the head, the snake has to come and get it */
+        let distance = self.apple.manhattan_distance(self.head);
+        let closer = Direction::MOVES.into_iter().map(|dir| self.apple.move_towards(dir)).find(|pos| {
+            pos.manhattan_distance(self.head) < distance && *pos != self.head && !self.more_apples.contains(pos)
+                && self.field.coordinate_in_bounds(*pos) && self.field.free_at(*pos)
+        });
+        if let Some(pos) = closer {
+            self.apple = pos;
+            /* measure path_efficiency() from here, the snake can't be
+             * blamed for the way the apple used to be */
+            self.apple_placed_at = self.moves;
+            self.apple_placed_distance = self.head.manhattan_distance(pos) as u32;
+        }
+    }
+    /* Let uneaten apples despawn after lifetime moves and respawn
+     * elsewhere, each one costing penalty points off the score */
+    pub fn set_apple_lifetime(&mut self, lifetime:Option<u32>, penalty:u32) {
+        self.apple_lifetime = lifetime;
+        self.miss_penalty = penalty;
+        self.apple_age = 0;
+    }
+    fn age_apple(&mut self) {
+        let Some(lifetime) = self.apple_lifetime else {
+            return;
+        };
+        self.apple_age += 1;
+        if self.apple_age < lifetime {
+            return;
+        }
+        if !self.apple_sequence.is_empty() {
+            /* A level's apple comes back where it was, moving on to the
+             * next one would skip it */
+            self.apple_placed();
+        } else {
+            /* Block the old spot so the new apple can't land right back
+             * on it. With nowhere else to go the apple just stays put. */
+            let old = self.apple;
+            self.field.set_direction_at(old, Direction::Wall);
+            self.place_new_apple();
+            self.field.set_direction_at(old, Direction::Null);
+        }
+        self.apple_age = 0;
+        self.missed_apples += 1;
+    }
+    pub fn missed_apples(&self) -> u32 {
+        self.missed_apples
+    }
+    /* Apples eaten minus the penalty for the ones that got away */
+    pub fn score(&self) -> i64 {
+        self.apples as i64 - (self.missed_apples * self.miss_penalty) as i64
+    }
+    pub fn head(&self) -> Coordinate {
+        self.head
+    }
+    pub fn apple(&self) -> Coordinate {
+        self.apple
+    }
+    pub fn field(&self) -> &Field {
+        &self.field
+    }
+    pub fn apples(&self) -> u32 {
+        self.apples
+    }
+    pub fn moves(&self) -> u32 {
+        self.moves
+    }
+    pub fn length(&self) -> usize {
+        self.length
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+    pub fn set_theme(&mut self, theme:Theme) {
+        self.theme = theme;
+    }
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode:GameMode) {
+        self.mode = mode;
+    }
+    /* Declare the game won once the snake is this long, None to play
+     * until the board is full */
+    pub fn set_length_goal(&mut self, goal:Option<usize>) {
+        self.length_goal = goal;
+    }
+    pub fn is_won(&self) -> bool {
+        let level_cleared = !self.apple_sequence.is_empty() && self.apples as usize >= self.apple_sequence.len();
+        /* in ghost mode the snake may lie over itself and be longer than the board */
+        self.length - self.field.buried_count() == self.area() || self.length_goal.is_some_and(|goal| self.length >= goal) || level_cleared
+    }
+    /* Run check_invariants() after every step */
+    pub fn set_debug_invariants(&mut self, enabled:bool) {
+        self.debug_invariants = enabled;
+    }
+    pub fn set_edges(&mut self, edges:EdgeModes) {
+        self.field.edges = edges;
+    }
+    pub fn body(&self) -> Vec<Coordinate> {
+        self.field.body(self.head)
+    }
+    /* Top left and bottom right corner of the smallest box around the
+     * whole body */
+    pub fn body_bounds(&self) -> (Coordinate, Coordinate) {
+        self.field.iter_chain(self.head).fold((self.head, self.head), |(min, max), pos| {
+            (Coordinate{x: min.x.min(pos.x), y: min.y.min(pos.y)}, Coordinate{x: max.x.max(pos.x), y: max.y.max(pos.y)})
+        })
+    }
+    /* Has the snake walled itself into a pocket? True when there are free
+     * cells the head can no longer reach. */
+    pub fn is_in_enclosed_region(&self) -> bool {
+        self.field.reachable_count(self.head) < self.field.free_cells().count()
+    }
+    /* Manhattan distance from the head to the nearest wall or segment
+     * of the body, not counting the neck right behind the head. */
+    pub fn distance_to_danger(&self) -> usize {
+        let dimension = self.field.dimension;
+        let wall = [self.head.x+1, self.head.y+1, dimension.x-self.head.x, dimension.y-self.head.y];
+        let body = self.body().into_iter().skip(2).map(|segment| self.head.manhattan_distance(segment));
+        wall.into_iter().chain(body).min().unwrap_or(0) as usize
+    }
+    /* Directions the head can move in without hitting a wall or the body */
+    pub fn safe_moves(&self) -> Vec<Direction> {
+        Direction::MOVES.into_iter().filter(|dir| {
+            self.is_legal_move(*dir) && self.field.free_at(self.field.move_towards(self.head, *dir))
+        }).collect()
+    }
+    /* safe_moves() with the ones that bring the head closest to target
+     * first, ties in the usual order */
+    pub fn legal_directions_toward(&self, target:Coordinate) -> Vec<Direction> {
+        let mut moves = self.safe_moves();
+        moves.sort_by_key(|dir| self.field.move_towards(self.head, *dir).manhattan_distance(target));
+        moves
+    }
+    /* Have the snake face dir before its first move, as if it just came
+     * from the opposite side. Snakes that keep their heading go that way
+     * first; a lone head may still turn straight back. */
+    pub fn set_initial_heading(&mut self, dir:Direction) -> Result<(), String> {
+        if self.moves > 0 {
+            return Err("the snake already moved".to_string());
+        }
+        if !dir.is_movement() {
+            return Err(format!("{:?} is not a heading", dir));
+        }
+        if !self.field.coordinate_in_bounds(self.field.move_towards(self.head, dir)) {
+            return Err(format!("heading {:?} from {} runs off the board", dir, self.head));
+        }
+        self.last_direction = Some(dir);
+        Ok(())
+    }
+    /* Direction of the most recently committed move */
+    pub fn last_direction(&self) -> Option<Direction> {
+        self.last_direction
+    }
+    pub fn apples_eaten(&self) -> u32 {
+        self.apples
+    }
+    /* How often the snake went left, right, up and down */
+    pub fn direction_counts(&self) -> [u32; 4] {
+        self.direction_counts
+    }
+    /* Where the most recently eaten apple was */
+    pub fn last_apple(&self) -> Option<Coordinate> {
+        self.last_apple
+    }
+    /* How far along the snake is towards filling the board, 0 to 1 */
+    pub fn completion_ratio(&self) -> f32 {
+        let (width, height) = (self.field.dimension.x as usize, self.field.dimension.y as usize);
+        (self.length - 1) as f32 / max_theoretical_apples(width, height).max(1) as f32
+    }
+    /* completion_ratio() as a bar of width cells, rounded down so a
+     * full bar means a won game */
+    pub fn progress_bar(&self, width:usize) -> String {
+        let filled = ((self.completion_ratio() * width as f32) as usize).min(width);
+        format!("[{}{}]", "█".repeat(filled), "░".repeat(width - filled))
+    }
+    pub fn area(&self) -> usize {
+        (self.field.dimension.x * self.field.dimension.y) as usize
+    }
+    pub fn cell(&self, pos:Coordinate) -> Cell {
+        if !self.field.coordinate_in_bounds(pos) {
+            return Cell::OutOfBounds;
+        }
+        if pos == self.head {
+            return Cell::Head;
+        }
+        if self.field.portal_exit(pos).is_some() {
+            return Cell::Portal;
+        }
+        match self.field.get_direction_at(pos) {
+            Direction::Null if self.is_apple(pos) => Cell::Apple,
+            Direction::Null => Cell::Empty,
+            Direction::Wall => Cell::Wall,
+            Direction::End  => Cell::Tail,
+            dir             => Cell::Body(dir),
+        }
+    }
+    /* Cells with neither snake, wall nor apple on them, without looking
+     * at every one */
+    pub fn free_cells(&self) -> usize {
+        let apple = self.field.free_at(self.apple) as usize;
+        let body = self.length - self.field.buried_count();
+        self.area() - body - self.field.wall_count() - apple - self.more_apples.len()
+    }
+    /* A random maze from the game's seed, see Field::generate_walls().
+     * Should a wall land on the apple, it moves elsewhere. */
+    pub fn generate_walls(&mut self, density:f32) -> usize {
+        let placed = self.field.generate_walls(&mut self.rng, density);
+        if !self.field.free_at(self.apple) {
+            self.place_new_apple();
+        }
+        self.top_up_apples();
+        placed
+    }
+    /* Link two free cells, see Field::add_portal() */
+    pub fn add_portal(&mut self, a:Coordinate, b:Coordinate) -> Result<(), String> {
+        if let Some(apple) = [a, b].into_iter().find(|pos| self.is_apple(*pos)) {
+            return Err(format!("an apple is on {}", apple));
+        }
+        self.field.add_portal(a, b)
+    }
+    /* Block a free cell for the next ticks moves */
+    pub fn add_temporary_wall(&mut self, pos:Coordinate, ticks:u32) -> bool {
+        if !self.field.coordinate_in_bounds(pos) || !self.field.free_at(pos) || self.is_apple(pos) || ticks == 0 {
+            return false;
+        }
+        self.field.set_direction_at(pos, Direction::Wall);
+        self.temporary_walls.push((pos, ticks));
+        true
+    }
+    fn tick_temporary_walls(&mut self) {
+        for (pos, ticks) in self.temporary_walls.iter_mut() {
+            *ticks -= 1;
+            if *ticks == 0 {
+                self.field.set_direction_at(*pos, Direction::Null);
+            }
+        }
+        self.temporary_walls.retain(|(_, ticks)| *ticks > 0);
+    }
+    /* Move the head one cell in direction dir and grow or drag the
+     * tail along. */
+    pub fn step(&mut self, dir:Direction) -> StepOutcome {
+        self.history.push(dir);
+        let outcome = self.move_head(dir);
+        if outcome.committed() {
+            if let Some(index) = Direction::MOVES.iter().position(|d| *d == dir) {
+                self.direction_counts[index] += 1;
+            }
+            self.tick_temporary_walls();
+        }
+        if outcome == StepOutcome::Moved {
+            self.age_apple();
+            self.pull_apple();
+        }
+        if self.debug_invariants {
+            self.check_invariants();
+        }
+        outcome
+    }
+    /* The seed, size and moves so far. That is enough to play the game
+     * again only with the rules of a plain init_seeded() game, so games
+     * with any other rules are refused. */
+    pub fn record(&self, snake:&str) -> Result<Replay, String> {
+        self.has_plain_rules()?;
+        if snake.contains(['\n', '\r']) {
+            return Err(format!("a replay header can't hold the line break in snake name {:?}", snake));
+        }
+        Ok(Replay{
+            seed: self.seed,
+            width: self.field.dimension.x as usize,
+            height: self.field.dimension.y as usize,
+            snake: snake.to_string(),
+            directions: self.history.clone(),
+        })
+    }
+    /* Err naming the first rule a Replay can't carry */
+    fn has_plain_rules(&self) -> Result<(), String> {
+        let changed = [
+            (self.picked_start, "start positions"),
+            (self.field.edges != EdgeModes::default(), "edges"),
+            (self.field.wall_count() > 0 || !self.temporary_walls.is_empty(), "walls"),
+            (!self.field.portals.is_empty(), "portals"),
+            (self.apple_placement != ApplePlacement::Uniform, "apple placement"),
+            (!self.apple_sequence.is_empty() || !self.apple_pattern.is_empty(), "placed apples"),
+            (self.apple_lifetime.is_some(), "apple lifetime"),
+            (self.apple_magnet.is_some(), "apple magnet"),
+            (self.apple_count > 1, "apple count"),
+            (self.mode != GameMode::Classic, "game mode"),
+            (self.length_goal.is_some(), "length goal"),
+        ];
+        match changed.iter().find(|(changed, _)| *changed) {
+            Some((_, rule)) => Err(format!("a replay can't record a game with custom {}", rule)),
+            None            => Ok(()),
+        }
+    }
+    /* Panic when the field encoding no longer adds up. Too slow to run
+     * every step in a normal game, see debug_invariants. */
+    pub fn check_invariants(&self) {
+        /* in ghost mode some segments lie under others */
+        let cells = self.field.directions.iter().flatten().chain(self.field.buried.iter().map(|(_, dir)| dir));
+        let ends = cells.clone().filter(|dir| dir.is_tail()).count();
+        let occupied = cells.clone().filter(|dir| dir.is_snake()).count();
+        let state = format!("head {}, apple {}, length {}, moves {}, {} tail ends, {} occupied cells",
+            self.head, self.apple, self.length, self.moves, ends, occupied);
+        if ends != 1 {
+            panic!("Expected exactly one tail end: {}", state);
+        }
+        if occupied != self.length || self.field.iter_chain(self.head).count() != self.length {
+            panic!("Occupied cells don't match the snake length: {}", state);
+        }
+        let head = self.field.get_direction_at(self.head);
+        if !head.is_snake() {
+            panic!("Head is not on the snake: {}", state);
+        }
+        /* after a win there may be no apple left to put out */
+        let board_full = self.field.free_cells().next().is_none();
+        if !board_full && !self.is_won() && !self.field.free_at(self.apple) {
+            panic!("Apple is not on a free cell: {}", state);
+        }
+        if self.more_apples.iter().any(|pos| !self.field.free_at(*pos) || *pos == self.apple) {
+            panic!("An extra apple is not on a free cell of its own: {}", state);
+        }
+    }
+    /* Would step() accept this move? */
+    pub fn is_legal_move(&self, dir:Direction) -> bool {
+        self.collision(dir).is_none()
+    }
+    /* The outcome that ends the game if the head moved in direction dir */
+    pub fn collision(&self, dir:Direction) -> Option<StepOutcome> {
+        if !dir.is_movement() {
+            return Some(StepOutcome::Gibberish);
+        }
+        /* Turning right back is moving into the neck, even when that is
+         * the tail. A lone head has no neck and may go any way. */
+        let ghost = self.mode == GameMode::Ghost;
+        if !ghost && self.length > 1 && self.last_direction == Some(dir.invert()) {
+            return Some(StepOutcome::AteSnake);
+        }
+        let head = self.field.move_towards(self.head, dir);
+        if !self.field.coordinate_in_bounds(head) || self.field.get_direction_at(head) == Direction::Wall {
+            return Some(StepOutcome::CrashedInWall);
+        }
+        /* A lone head coming back onto itself, over a portal or around a
+         * board one cell across, is its own tail but can't move out of
+         * its own way */
+        if head == self.head {
+            return Some(StepOutcome::AteSnake);
+        }
+        /* Moving into the tail is fine, it moves out of the way */
+        if !ghost && !self.field.get_direction_at(head).is_tail() && !self.field.free_at(head) {
+            return Some(StepOutcome::AteSnake);
+        }
+        None
+    }
+    fn move_head(&mut self, dir:Direction) -> StepOutcome {
+        if let Some(outcome) = self.collision(dir) {
+            return outcome;
+        }
+        let head = self.field.move_towards(self.head, dir);
+        if self.field.get_direction_at(head).is_tail() {
+            /* This is a corner case where we follow our tail closely. We
+             * must be careful not to overwrite tail. On the flip side we
+             * don't have to check for apples or collisions. */
+            self.moves += 1;
+            self.last_direction = Some(dir);
+            let _dropped = self.field.drop_last_in_chain(self.head);
+            self.field.set_direction_at(head, dir.invert());
+            self.set_head(head); /* we *might* have overwritten tail */
+            return StepOutcome::Moved;
+        }
+        self.moves += 1;
+        self.last_direction = Some(dir);
+        if self.field.get_direction_at(head).is_snake() {
+            self.field.bury(head);
+        }
+        self.field.set_direction_at(head, dir.invert());
+        self.set_head(head);
+
+        if let Some(eaten) = self.more_apples.iter().position(|pos| *pos == self.head) {
+            self.more_apples.swap_remove(eaten);
+            self.apples += 1;
+            self.length += 1;
+            self.top_up_apples();
+            return if self.is_won() { StepOutcome::Won } else { StepOutcome::AteApple };
+        }
+        //are we on a apple now?
+        if self.head != self.apple {
+            let _dropped = self.field.drop_last_in_chain(self.head);
+            return StepOutcome::Moved;
+        }
+        self.apples += 1;
+        self.last_apple = Some(self.apple);
+        self.moves_to_apples += self.moves - self.apple_placed_at;
+        self.distance_to_apples += self.apple_placed_distance;
+        if self.mode == GameMode::Teleport {
+            return self.teleport();
+        }
+        self.length += 1;
+        /* Filling the last free cell is a win, without looking for room
+         * for another apple that isn't there. Any other win still gets a
+         * new apple in case the game goes on. */
+        if self.free_cells() == 0 && self.more_apples.is_empty() {
+            return StepOutcome::Won;
+        }
+        let placed = self.place_new_apple();
+        self.top_up_apples();
+        if self.is_won() || !placed {
+            return StepOutcome::Won;
+        }
+        StepOutcome::AteApple
+    }
+    /* Leave the body behind as walls and start over somewhere else */
+    fn teleport(&mut self) -> StepOutcome {
+        /* No room to start over, the snake just grew into the last cell */
+        let Some(head) = self.field.random_available(&mut self.rng) else {
+            self.length += 1;
+            return StepOutcome::Won;
+        };
+        for segment in self.body() {
+            self.field.set_direction_at(segment, Direction::Wall);
+        }
+        self.trail.clear();
+        self.head = head;
+        self.field.set_direction_at(self.head, Direction::End);
+        self.length = 1;
+        /* the walls or the new head may have landed on other apples */
+        self.top_up_apples();
+        if !self.place_new_apple() {
+            return StepOutcome::Won;
+        }
+        StepOutcome::AteApple
+    }
+    /* Moves needed to reach the apple walking around the body, None if
+     * the apple is walled off. The body is treated as if it stands still. */
+    pub fn path_distance_to_apple(&self) -> Option<usize> {
+        self.field.distance_map(self.head)[self.apple.y as usize][self.apple.x as usize]
+    }
+    pub fn perf_counters(&self) -> PerfCounters {
+        self.field.perf_counters()
+    }
+    /* FNV-1a over the grid, head, apple and counters. Unlike the std
+     * hashers this gives the same value on every platform and build, so
+     * it can be written down in regression checks. */
+    pub fn state_hash(&self) -> u64 {
+        let mut hash:u64 = 0xcbf29ce484222325;
+        let mut feed = |bytes:&[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        for row in &self.field.directions {
+            for dir in row {
+                feed(&[*dir as u8]);
+            }
+        }
+        for pos in [self.head].into_iter().chain(self.apple_positions()) {
+            feed(&(pos.x as i64).to_le_bytes());
+            feed(&(pos.y as i64).to_le_bytes());
+        }
+        feed(&self.apples.to_le_bytes());
+        feed(&self.moves.to_le_bytes());
+        feed(&(self.length as u64).to_le_bytes());
+        hash
+    }
+    /* Cells that differ from other, with what self and other hold there.
+     * For checking a step only touched the head, the new head cell and
+     * the tail. Boards of different size compare where they overlap. */
+    pub fn diff(&self, other:&Game) -> Vec<(Coordinate, Direction, Direction)> {
+        let rows = self.field.directions.iter().zip(&other.field.directions).enumerate();
+        rows.flat_map(|(y, (row, other_row))| {
+            row.iter().zip(other_row).enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(move |(x, (old, new))| (Coordinate{x:x as isize, y:y as isize}, *old, *new))
+        }).collect()
+    }
+    /* One line to paste in a bug report: board size, seed, moves so far
+     * and the state hash, e.g. 5x5-s42-m17-9f3c2a1b0e4d5c6f */
+    pub fn fingerprint(&self) -> String {
+        format!("{}x{}-s{}-m{}-{:016x}", self.field.dimension.x, self.field.dimension.y, self.seed, self.moves, self.state_hash())
+    }
+    /* Top left cell and size of the part of the board that gets drawn:
+     * all of it, or theme.viewport centered on the head as far as the
+     * edges allow */
+    pub fn viewport(&self) -> (Coordinate, Coordinate) {
+        let dimension = self.field.dimension;
+        let Some((width, height)) = self.theme.viewport else {
+            return (Coordinate{x:0, y:0}, dimension);
+        };
+        let size = Coordinate{x: (width as isize).clamp(1, dimension.x), y: (height as isize).clamp(1, dimension.y)};
+        let corner = Coordinate{
+            x: (self.head.x - size.x/2).clamp(0, dimension.x - size.x),
+            y: (self.head.y - size.y/2).clamp(0, dimension.y - size.y),
+        };
+        (corner, size)
+    }
+    fn draw_border(&self, out:&mut impl std::io::Write, left:&str, horizontal:&str, right:&str) -> std::io::Result<()> {
+        write!(out, "  {}", left)?; for _ in 0..self.viewport().1.x*self.theme.cell_width() as isize { write!(out, "{}", horizontal)?; } writeln!(out, "{}", right)
+    }
+    #[cfg(feature = "image")]
+    fn color_at(&self, pos:Coordinate) -> [u8; 3] {
+        if pos == self.head {
+            return [0x2e, 0x7d, 0x32];
+        }
+        if self.is_apple(pos) {
+            return [0xd3, 0x2f, 0x2f];
+        }
+        if self.field.portal_exit(pos).is_some() {
+            return [0x7b, 0x1f, 0xa2];
+        }
+        match self.field.get_direction_at(pos) {
+            Direction::Null => [0xff, 0xff, 0xff],
+            Direction::Wall => [0x42, 0x42, 0x42],
+            _               => [0x81, 0xc7, 0x84],
+        }
+    }
+    /* Save the board as a PNG with every cell cell_px pixels square,
+     * theme.origin cells of blank margin to the left and on top */
+    #[cfg(feature = "image")]
+    pub fn write_png(&self, path:impl AsRef<std::path::Path>, cell_px:u32) -> Result<(), String> {
+        let (left, top) = (self.theme.origin.0 as u32, self.theme.origin.1 as u32);
+        let (width, height) = (self.field.dimension.x as u32 + left, self.field.dimension.y as u32 + top);
+        let image = image::RgbImage::from_fn(width*cell_px, height*cell_px, |x, y| {
+            let (x, y) = (x/cell_px, y/cell_px);
+            if x < left || y < top {
+                return image::Rgb([0xff, 0xff, 0xff]);
+            }
+            image::Rgb(self.color_at(Coordinate{x: (x - left) as isize, y: (y - top) as isize}))
+        });
+        image.save(path).map_err(|err| err.to_string())
+    }
+    pub fn draw(&self) {
+        self.draw_to(&mut std::io::stdout().lock()).expect("failed to draw to stdout");
+    }
+    /* The frame draw() prints, as a string */
+    pub fn render(&self) -> String {
+        let mut frame = Vec::new();
+        self.draw_to(&mut frame).expect("writing to a Vec never fails");
+        String::from_utf8(frame).expect("frames are valid UTF-8")
+    }
+    /* Shifted right and down by theme.origin */
+    pub fn draw_to(&self, out:&mut impl std::io::Write) -> std::io::Result<()> {
+        let (x, y) = self.theme.origin;
+        if (x, y) == (0, 0) {
+            return self.draw_board_to(out);
+        }
+        for _ in 0..y {
+            writeln!(out)?;
+        }
+        let indent = " ".repeat(x * self.theme.cell_width());
+        self.draw_board_to(&mut Indented{out, indent:indent.as_bytes(), line_start:true})
+    }
+    fn draw_board_to(&self, out:&mut impl std::io::Write) -> std::io::Result<()> {
+        /* drawing reads the grid as well, which is not the snake's doing */
+        let perf = self.perf_counters();
+        let (corner, size) = self.viewport();
+        write!(out, "   ")?; for i in corner.x..corner.x+size.x { write!(out, "{}", self.theme.cell(i%10))?; } writeln!(out)?;
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = self.theme.border.glyphs();
+        let [seam_horizontal, seam_vertical] = self.theme.border.seam_glyphs();
+        let edges = self.field.edges;
+        let side = |mode:EdgeMode, solid, seam| if mode == EdgeMode::Wrap { seam } else { solid };
+        let (top, bottom) = (side(edges.top, horizontal, seam_horizontal), side(edges.bottom, horizontal, seam_horizontal));
+        let (left, right) = (side(edges.left, vertical, seam_vertical), side(edges.right, vertical, seam_vertical));
+        self.draw_border(out, top_left, top, top_right)?;
+        let heat = self.theme.show_heatmap.then(|| self.field.distance_map(self.head));
+        let farthest = heat.iter().flatten().flatten().flatten().copied().max().unwrap_or(0);
+        for (y, row) in self.field.directions.iter().enumerate().skip(corner.y as usize).take(size.y as usize) {
+            write!(out, "{} {}", y%10, left)?;
+            for (x, dir) in row.iter().enumerate().skip(corner.x as usize).take(size.x as usize) {
+                let pos = Coordinate{x:x as isize, y:y as isize};
+                if pos == self.head {
+                    write!(out, "{}", self.theme.cell('#'))?;
+                } else if self.is_apple(pos) {
+                    write!(out, "{}", self.theme.cell('ø'))?;
+                } else if let Some(pair) = self.field.portals.iter().position(|&(a, b)| a == pos || b == pos) {
+                    write!(out, "{}", self.theme.cell(format!("\x1b[35m{}\x1b[0m", (b'A' + (pair % 26) as u8) as char)))?;
+                } else if let Some(glyph) = self.trail_glyph(pos) {
+                    write!(out, "{}", self.theme.cell(glyph))?;
+                } else if !self.theme.show_body_arrows && dir.is_snake() {
+                    write!(out, "{}", self.theme.cell('■'))?;
+                } else if let Some(heat) = heat.as_ref().filter(|_| dir.is_empty()) {
+                    write!(out, "{}", self.theme.cell(Game::heatmap_glyph(heat[y][x], farthest)))?;
+                } else if let Some(next) = self.cycle_overlay_at(pos).filter(|_| self.theme.show_cycle && dir.is_empty()) {
+                    write!(out, "{}", self.theme.cell(format!("\x1b[2m{}\x1b[0m", next)))?;
+                } else {
+                    write!(out, "{}", self.theme.cell(dir.invert()))?;
+                }
+            }
+            writeln!(out, "{}", right)?;
+        }
+        self.draw_border(out, bottom_left, bottom, bottom_right)?;
+        let off_screen = if self.apple.x < corner.x {
+            Some(Direction::Left)
+        } else if self.apple.x >= corner.x + size.x {
+            Some(Direction::Right)
+        } else if self.apple.y < corner.y {
+            Some(Direction::Up)
+        } else if self.apple.y >= corner.y + size.y {
+            Some(Direction::Down)
+        } else {
+            None
+        };
+        if let Some(dir) = off_screen {
+            writeln!(out, "Apple off screen {}", dir)?;
+        }
+        writeln!(out, "Apples: {}, Moves: {}, Moves/apple: {}", self.apples, self.moves, self.moves as f32 / self.apples as f32)?;
+        if self.theme.progress_bar_width > 0 {
+            writeln!(out, "{} {:.0}%", self.progress_bar(self.theme.progress_bar_width), self.completion_ratio() * 100.0)?;
+        }
+        if self.apple_lifetime.is_some() {
+            writeln!(out, "Missed apples: {}, Score: {}", self.missed_apples, self.score())?;
+        }
+        match self.path_distance_to_apple() {
+            Some(distance) => writeln!(out, "Path to apple: {}", distance)?,
+            None           => writeln!(out, "Path to apple: blocked")?,
+        }
+        if cfg!(feature = "perf") {
+            writeln!(out, "Grid reads/move: {}, writes/move: {}", perf.reads as f32 / self.moves as f32, perf.writes as f32 / self.moves as f32)?;
+        }
+        #[cfg(feature = "perf")]
+        self.field.perf.set(perf);
+        Ok(())
+    }
+}
+
+/* Everything needed to set up the exact same game again, meant to be
+ * shared as a JSON file. Missing keys take their default. */
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    /* as numbered by choose_snake() */
+    pub snake: u32,
+    pub edges: EdgeModes,
+    pub apples: ApplePlacement,
+    pub mode: GameMode,
+    pub length_goal: Option<usize>,
+    /* milliseconds between moves when drawing */
+    pub speed: u64,
+    pub theme: Theme,
+    /* share of the board to fill with random walls, see
+     * Game::generate_walls() */
+    pub wall_density: f32,
+    /* see Game::set_apple_magnet() */
+    pub apple_magnet: Option<u32>,
+    /* one apple out for every so many cells, see apples_for_area() */
+    pub cells_per_apple: Option<usize>,
+}
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig{
+            width: 5,
+            height: 5,
+            seed: 42,
+            snake: 4,
+            edges: EdgeModes::default(),
+            apples: ApplePlacement::Uniform,
+            mode: GameMode::Classic,
+            length_goal: None,
+            speed: 50,
+            theme: Theme::default(),
+            wall_density: 0.0,
+            apple_magnet: None,
+            cells_per_apple: None,
+        }
+    }
+}
+impl GameConfig {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a config always serializes")
+    }
+    pub fn from_json(text:&str) -> Result<GameConfig, String> {
+        serde_json::from_str(text).map_err(|err| err.to_string())
+    }
+    pub fn run_options(&self) -> RunOptions {
+        RunOptions{draw: true, delay: time::Duration::from_millis(self.speed), ..RunOptions::default()}
+    }
+}
+
+/* One move of a game as a single line of JSON, for a frontend following
+ * along. The body is what the grid holds from the head to the tail, one
+ * Direction::to_char() per segment, so the head and the letters are
+ * enough to lay it out again. */
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MoveRecord {
+    pub moves: u32,
+    pub head: Coordinate,
+    pub apple: Coordinate,
+    pub body: String,
+    pub outcome: StepOutcome,
+}
+impl MoveRecord {
+    pub fn new(game:&Game, outcome:StepOutcome) -> MoveRecord {
+        let body = game.field.body(game.head).into_iter().map(|pos| game.field.get_direction_at(pos).to_char()).collect();
+        MoveRecord{moves: game.moves, head: game.head, apple: game.apple, body, outcome}
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a move always serializes")
+    }
+}
+
+/* A game is fully determined by its seed, its size and the moves made.
+ * As text a replay is a header line followed by one letter per move:
+ *
+ *   seed=42 width=5 height=5 snake=greedy
+ *   RRDDLU
+ *
+ * The snake name comes last and runs to the end of the line, so it may
+ * hold spaces.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub snake: String,
+    pub directions: Vec<Direction>,
+}
+impl Replay {
+    const LINE_LENGTH: usize = 72;
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("seed={} width={} height={} snake={}\n", self.seed, self.width, self.height, self.snake);
+        for line in self.directions.chunks(Replay::LINE_LENGTH) {
+            text.extend(line.iter().map(|dir| dir.to_char()));
+            text.push('\n');
+        }
+        text
+    }
+    pub fn from_text(text:&str) -> Result<Replay, String> {
+        let mut lines = text.lines();
+        let header = lines.next().ok_or("empty replay")?;
+        let mut replay = Replay{seed: 0, width: 0, height: 0, snake: String::new(), directions: Vec::new()};
+        let (header, snake) = header.split_once("snake=").unwrap_or((header, ""));
+        replay.snake = snake.to_string();
+        for field in header.split_whitespace() {
+            let (key, value) = field.split_once('=').ok_or(format!("malformed header field '{}'", field))?;
+            let not_a_number = |_| format!("'{}' is not a number", value);
+            match key {
+                "seed"   => replay.seed = value.parse().map_err(not_a_number)?,
+                "width"  => replay.width = value.parse().map_err(not_a_number)?,
+                "height" => replay.height = value.parse().map_err(not_a_number)?,
+                _        => return Err(format!("unknown header field '{}'", key)),
+            }
+        }
+        for c in lines.flat_map(|line| line.chars()).filter(|c| !c.is_whitespace()) {
+            replay.directions.push(Direction::from_char(c).ok_or(format!("'{}' is not a direction", c))?);
+        }
+        Ok(replay)
+    }
+}
+
+/* A hand made puzzle: walls, where the snake starts and the apples it
+ * has to eat in order. As text one character per cell:
+ *
+ *   #######
+ *   #@..#2#
+ *   #...1.#
+ *   #######
+ *
+ * # is a wall, . a free cell, @ the head and 1 to 9 the apples in the
+ * order they appear.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Level {
+    pub width: usize,
+    pub height: usize,
+    pub walls: Vec<Coordinate>,
+    pub head: Coordinate,
+    pub apples: Vec<Coordinate>,
+}
+impl Level {
+    pub fn from_text(text:&str) -> Result<Level, String> {
+        let rows: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+        let width = rows.first().ok_or("empty level")?.chars().count();
+        let mut walls = Vec::new();
+        let mut head = None;
+        let mut apples = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(format!("row {} is not {} cells wide", y, width));
+            }
+            for (x, c) in row.chars().enumerate() {
+                let pos = Coordinate{x:x as isize, y:y as isize};
+                match c {
+                    '#' => walls.push(pos),
+                    '.' => (),
+                    '@' if head.is_none() => head = Some(pos),
+                    '@' => return Err(format!("second head at {}", pos)),
+                    '1'..='9' => apples.push((c, pos)),
+                    _ => return Err(format!("'{}' at {} is not a level cell", c, pos)),
+                }
+            }
+        }
+        apples.sort_by_key(|(number, _)| *number);
+        if apples.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err("apple numbers must be unique".to_string());
+        }
+        Ok(Level{
+            width,
+            height: rows.len(),
+            walls,
+            head: head.ok_or("no head in level")?,
+            apples: apples.into_iter().map(|(_, pos)| pos).collect(),
+        })
+    }
+}
+
+pub trait Snake {
+    fn name(&self) -> &'static str;
+    fn init(&mut self, game:&Game);
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction>;
+    /* Called after every apple the snake eats, to drop stale plans */
+    fn on_apple_eaten(&mut self, _game:&Game) { }
+    /* For snakes that follow a fixed cycle: its cells in visiting order */
+    fn cycle(&self, _game:&Game) -> Option<Vec<Coordinate>> { None }
+    /* Same game, same moves. Seeds only reproduce a run if this holds. */
+    fn is_deterministic(&self) -> bool { true }
+    /* Whether the snake can play under config at all, and if not why */
+    fn supports(&self, _config:&GameConfig) -> Result<(), String> { Ok(()) }
+}
+
+/* Replaying a seed is pointless if the snake plays differently each time */
+fn warn_if_nondeterministic(snake:&dyn Snake) {
+    if !snake.is_deterministic() {
+        eprintln!("The {} snake does not play the same way twice, this run will not match the earlier one.", snake.name());
+    }
+}
+
+/* Picks any direction at random, seeded from the game so the same seed
+ * gives the same silly run */
+pub struct SillySnake {
+    rng: StdRng,
+}
+impl Default for SillySnake {
+    fn default() -> SillySnake {
+        SillySnake::new()
+    }
+}
+impl SillySnake {
+    pub fn new() -> SillySnake {
+        SillySnake{rng: StdRng::seed_from_u64(0)}
+    }
+}
+impl Snake for SillySnake {
+    fn name(&self) -> &'static str { "silly" }
+    fn init(&mut self, game:&Game) {
+        self.rng = StdRng::seed_from_u64(game.seed());
+    }
+    fn choose_direction(&mut self, _game:&Game) -> Option<Direction> {
+        Some(Direction::random(&mut self.rng))
+    }
+}
+
+/* Like the silly snake, but draws directions from a shuffle bag so
+ * every four moves try each direction exactly once, in random order. */
+pub struct ShuffleBagSnake {
+    bag: Vec<Direction>,
+    rng: StdRng,
+}
+impl Default for ShuffleBagSnake {
+    fn default() -> ShuffleBagSnake {
+        ShuffleBagSnake::new()
+    }
+}
+impl ShuffleBagSnake {
+    pub fn new() -> ShuffleBagSnake {
+        ShuffleBagSnake{bag: Vec::new(), rng: StdRng::from_entropy()}
+    }
+}
+impl Snake for ShuffleBagSnake {
+    fn name(&self) -> &'static str { "shuffle-bag" }
+    fn is_deterministic(&self) -> bool { false }
+    fn init(&mut self, _game:&Game) {
+        self.bag.clear();
+    }
+    fn choose_direction(&mut self, _game:&Game) -> Option<Direction> {
+        if self.bag.is_empty() {
+            self.bag = Direction::MOVES.to_vec();
+            self.bag.shuffle(&mut self.rng);
+        }
+        self.bag.pop()
+    }
+}
+
+pub struct GreedySnake;
+impl Snake for GreedySnake {
+    fn name(&self) -> &'static str { "greedy" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let delta = game.head.difference(game.apple);
+        Some(if (delta.x.abs() < delta.y.abs() || delta.y == 0) && delta.x != 0 {
+        //if delta.x.abs() > delta.y.abs() {
+            if delta.x > 0 { Direction::Right } else { Direction::Left }
+        } else {
+            if delta.y > 0 { Direction::Down } else { Direction::Up }
+        })
+    }
+}
+pub struct GreedyPickySnake;
+impl GreedyPickySnake {
+    /* Best move first, the move straight away from the apple last. One
+     * horizontal and one vertical direction plus their inverses, so the
+     * result always holds each of the four directions exactly once, even
+     * when snake and apple coincide. */
+    fn prioritize(snake:Coordinate, apple:Coordinate) -> [Direction; 4] {
+        let delta = snake.difference(apple);
+        let horizontal = if delta.x > 0 { Direction::Right } else { Direction::Left };
+        let vertical   = if delta.y > 0 { Direction::Down } else { Direction::Up };
+        let (first, second) = if (delta.x.abs() < delta.y.abs() || delta.y == 0) && delta.x != 0 {
+            (horizontal, vertical)
+        } else {
+            (vertical, horizontal)
+        };
+        [first, second, second.invert(), first.invert()]
+    }
+    fn available(game:&Game, dir:Direction) -> bool {
+        let pos = game.head.move_towards(dir);
+        game.field.coordinate_in_bounds(pos) && game.field.free_at(pos)
+    }
+}
+impl Snake for GreedyPickySnake {
+    fn name(&self) -> &'static str { "greedy-picky" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let preferred = GreedyPickySnake::prioritize(game.head, game.apple).into_iter();
+        let mut available = preferred.filter(|dir| GreedyPickySnake::available(game, *dir));
+        /* Should prioritize() ever leave out a direction, or the only way
+         * out is across a wrapping edge, take any move that is safe
+         * before giving up. */
+        available.next().or_else(|| game.safe_moves().into_iter().next())
+    }
+}
+
+/* Scores every direction by how far the apple is from where it leads,
+ * walls and body costing infinitely much, and takes the cheapest. Gives
+ * up only when every direction is deadly. */
+pub struct GreedyAvoidSelfSnake;
+impl GreedyAvoidSelfSnake {
+    fn cost(game:&Game, dir:Direction) -> Option<isize> {
+        game.is_legal_move(dir).then(|| game.field.move_towards(game.head, dir).manhattan_distance(game.apple))
+    }
+}
+impl Snake for GreedyAvoidSelfSnake {
+    fn name(&self) -> &'static str { "greedy-avoid-self" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        Direction::MOVES.into_iter()
+            .filter_map(|dir| GreedyAvoidSelfSnake::cost(game, dir).map(|cost| (cost, dir)))
+            .min_by_key(|(cost, _)| *cost)
+            .map(|(_, dir)| dir)
+    }
+}
+
+/* A winning strategy. However at a cost. Expected moves per apple
+ * works out to (w*h)/4 */
+pub struct HamiltonianSnake {
+    /* for every cell the way on along hamiltonian_cycle(), empty on
+     * boards without a cycle */
+    next: Vec<Vec<Direction>>,
+}
+impl Default for HamiltonianSnake {
+    fn default() -> HamiltonianSnake {
+        HamiltonianSnake::new()
+    }
+}
+impl Snake for HamiltonianSnake {
+    fn name(&self) -> &'static str { "hamiltonian" }
+    fn init(&mut self, game:&Game) {
+        let dimension = game.field.dimension;
+        self.next.clear();
+        if let Some(cycle) = HamiltonianSnake::cycle_cells(game) {
+            self.next = vec![vec![Direction::Null; dimension.x as usize]; dimension.y as usize];
+            for (i, pos) in cycle.iter().enumerate() {
+                let next = cycle[(i+1) % cycle.len()];
+                self.next[pos.y as usize][pos.x as usize] = pos.direction_to(next).expect("cycle cells are neighbours");
+            }
+        }
+    }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        Some(self.direction_from(game, game.head, game.apple))
+    }
+    fn cycle(&self, game:&Game) -> Option<Vec<Coordinate>> {
+        HamiltonianSnake::cycle_cells(game)
+    }
+    fn supports(&self, config:&GameConfig) -> Result<(), String> {
+        HamiltonianSnake::supports_board(self.name(), config)
+    }
+}
+impl HamiltonianSnake {
+    pub fn new() -> HamiltonianSnake {
+        HamiltonianSnake{next: Vec::new()}
+    }
+    /* Where to go from head on the way to target */
+    pub fn direction_from(&self, game:&Game, head:Coordinate, target:Coordinate) -> Direction {
+        match self.next.get(head.y as usize).and_then(|row| row.get(head.x as usize)) {
+            Some(&dir) => dir,
+            None       => HamiltonianSnake::next_hamiltonian_direction(game, head, target),
+        }
+    }
+    /* The cycle runs over every cell of the board, walls would cut it */
+    fn supports_board(name:&str, config:&GameConfig) -> Result<(), String> {
+        if config.wall_density > 0.0 {
+            return Err(format!("The {} snake needs a board without walls.", name));
+        }
+        match config.mode {
+            GameMode::Teleport => Err(format!("The {} snake needs a board without walls, teleport mode leaves them behind.", name)),
+            GameMode::Classic | GameMode::Ghost => Ok(()),
+        }
+    }
+    /* The cycle from the top left corner on, None when the board has no
+     * cycle and the snake takes a detour through the corner instead */
+    pub fn cycle_cells(game:&Game) -> Option<Vec<Coordinate>> {
+        hamiltonian_cycle(game.field.dimension.x as usize, game.field.dimension.y as usize)
+    }
+    /* Only for boards without a cycle: the same path one step at a time,
+     * with the detour through the corner on odd by odd boards */
+    fn next_hamiltonian_direction(game:&Game, head:Coordinate, target:Coordinate) -> Direction {
+        let x = head.x;
+        let y = head.y;
+        let w = game.field.dimension.x;
+        let h = game.field.dimension.y;
+
+        if y == 0 {
+            /* At the top row we go only left, then down */
+            if x > 0 { Direction::Left } else { Direction::Down }
+        } else if x == w-1 { //last column
+            /* In the last column go straight up then left OR
+             * wiggle upwards if column count (w) is odd. */
+            if !odd(w) { //straight up!
+                Direction::Up
+            } else {
+                if odd(h - y) {
+                    Direction::Up
+                } else {
+                    /*CORNER case if w*h is odd reroute path trough top right corner */
+                    if y == 1 && odd(w) && odd(h) && target.y == 0 { Direction::Up } else { Direction::Left }
+                }
+            }
+        } else if x == w-2 && odd(w) { //1 before last column
+            /* Wiggle back to last column  */
+            if !odd(h - y) { Direction::Up } else { Direction::Right }
+        } else if odd(x) {
+            /* Down on odd lines */
+            if y > 1 { Direction::Up } else { Direction::Right }
+        } else {
+            /* Up on even lines */
+            if y < h-1 { Direction::Down } else { Direction::Right }
+        }
+    }
+}
+
+pub struct ImpatientHamiltonianSnake {
+    path: HamiltonianSnake,
+}
+impl Default for ImpatientHamiltonianSnake {
+    fn default() -> ImpatientHamiltonianSnake {
+        ImpatientHamiltonianSnake::new()
+    }
+}
+impl Snake for ImpatientHamiltonianSnake {
+    fn name(&self) -> &'static str { "impatient-hamiltonian" }
+    fn init(&mut self, game:&Game) {
+        self.path.init(game);
+    }
+    /* propose greedy move, if after making that move can't follow
+     * a Hamiltonian path to the apple reject. */
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let preferred = GreedyPickySnake::prioritize(game.head, game.apple).into_iter();
+        let mut available = preferred.filter(|dir| GreedyPickySnake::available(game, *dir));
+        if let Some(dir) = available.next() { //only consider the first if list not empty
+            let pos = game.head.move_towards(dir);
+            /* and the cycle must not lead straight back, into the neck */
+            let turns_back = self.next_hamiltonian_move(game, pos, game.apple) == game.head;
+            if !turns_back && self.apple_on_path_to_tail(game, pos) {
+                return Some(dir);
+            }
+        }
+        Some(self.path.direction_from(game, game.head, game.apple))
+    }
+    fn cycle(&self, game:&Game) -> Option<Vec<Coordinate>> {
+        HamiltonianSnake::cycle_cells(game)
+    }
+    fn supports(&self, config:&GameConfig) -> Result<(), String> {
+        HamiltonianSnake::supports_board(self.name(), config)
+    }
+}
+impl ImpatientHamiltonianSnake {
+    pub fn new() -> ImpatientHamiltonianSnake {
+        ImpatientHamiltonianSnake{path: HamiltonianSnake::new()}
+    }
+    pub fn next_hamiltonian_move(&self, game:&Game, head:Coordinate, target:Coordinate) -> Coordinate {
+        head.move_towards(self.path.direction_from(game, head, target))
+    }
+    pub fn apple_on_path_to_tail(&self, game:&Game, head:Coordinate) -> bool {
+        let (tail, _) = game.field.find_last(game.head);
+        let mut pos = head;
+        let mut seen_apple = false;
+        while pos != tail {
+            if !game.field.free_at(pos) {
+                return false;
+            }
+            if pos == game.apple {
+                seen_apple = true;
+            }
+            pos = self.next_hamiltonian_move(game, pos, tail);
+        }
+        seen_apple
+    }
+}
+
+/* Follows the Hamiltonian cycle but eats the apple straight away when it
+ * is right next to the head, as long as the cycle from the apple onwards
+ * is still free all the way to the tail. After such a jump it simply
+ * carries on along the cycle from where it landed. */
+pub struct OpportunisticHamiltonianSnake {
+    /* the cells in cycle order and each cell's index in it, both empty
+     * on boards without a cycle */
+    cycle: Vec<Coordinate>,
+    order: Vec<Vec<usize>>,
+}
+impl Default for OpportunisticHamiltonianSnake {
+    fn default() -> OpportunisticHamiltonianSnake {
+        OpportunisticHamiltonianSnake::new()
+    }
+}
+impl OpportunisticHamiltonianSnake {
+    pub fn new() -> OpportunisticHamiltonianSnake {
+        OpportunisticHamiltonianSnake{cycle: Vec::new(), order: Vec::new()}
+    }
+    fn next_on_cycle(&self, pos:Coordinate) -> Coordinate {
+        self.cycle[(self.order[pos.y as usize][pos.x as usize] + 1) % self.cycle.len()]
+    }
+    /* How many steps along the cycle it takes to get from a to b */
+    fn cycle_distance(&self, a:Coordinate, b:Coordinate) -> usize {
+        let n = self.cycle.len();
+        (self.order[b.y as usize][b.x as usize] + n - self.order[a.y as usize][a.x as usize]) % n
+    }
+    fn free_from_apple_to_tail(&self, game:&Game) -> bool {
+        let (tail, _) = game.field.find_last(game.head);
+        let mut pos = self.next_on_cycle(game.apple);
+        while pos != tail {
+            if !game.field.free_at(pos) {
+                return false;
+            }
+            pos = self.next_on_cycle(pos);
+        }
+        true
+    }
+}
+impl Snake for OpportunisticHamiltonianSnake {
+    fn name(&self) -> &'static str { "opportunistic-hamiltonian" }
+    fn supports(&self, config:&GameConfig) -> Result<(), String> {
+        HamiltonianSnake::supports_board(self.name(), config)
+    }
+    fn init(&mut self, game:&Game) {
+        let dimension = game.field.dimension;
+        self.cycle = HamiltonianSnake::cycle_cells(game).unwrap_or_default();
+        self.order = vec![vec![0; dimension.x as usize]; dimension.y as usize];
+        for (i, pos) in self.cycle.iter().enumerate() {
+            self.order[pos.y as usize][pos.x as usize] = i;
+        }
+    }
+    fn cycle(&self, _game:&Game) -> Option<Vec<Coordinate>> {
+        Some(self.cycle.clone()).filter(|cycle| !cycle.is_empty())
+    }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        if self.cycle.is_empty() {
+            return Some(HamiltonianSnake::next_hamiltonian_direction(game, game.head, game.apple));
+        }
+        /* Only jump forward along the cycle, and not past the tail. Jumping
+         * back would put the body ahead of us. Every jump also leaves the
+         * skipped cells behind us, a full lap away should the next apple
+         * land there. That only pays off while the snake is short. */
+        let (tail, _) = game.field.find_last(game.head);
+        let forward = self.cycle_distance(game.head, game.apple) < self.cycle_distance(game.head, tail);
+        /* eating grows a neck where the head is now, the cycle must not
+         * lead straight back into it */
+        let turns_back = self.next_on_cycle(game.apple) == game.head;
+        if let Some(dir) = game.head.direction_to(game.apple) {
+            if (forward || tail == game.head) && !turns_back && game.length*4 < self.cycle.len() && self.free_from_apple_to_tail(game) {
+                return Some(dir);
+            }
+        }
+        game.head.direction_to(self.next_on_cycle(game.head))
+    }
+}
+
+/* Stays close to walls and its own body so the free space in the
+ * middle remains one big region. Only goes for the apple when two
+ * moves hug equally well. */
+pub struct WallHuggerSnake;
+impl Snake for WallHuggerSnake {
+    fn name(&self) -> &'static str { "wall-hugger" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        game.safe_moves().into_iter().min_by_key(|dir| {
+            let pos = game.head.move_towards(*dir);
+            (std::cmp::Reverse(WallHuggerSnake::blocked_neighbors(game, pos)), pos.manhattan_distance(game.apple))
+        })
+    }
+}
+impl WallHuggerSnake {
+    fn blocked_neighbors(game:&Game, pos:Coordinate) -> usize {
+        pos.neighbors().into_iter()
+            .filter(|n| !game.field.coordinate_in_bounds(*n) || !game.field.free_at(*n))
+            .count()
+    }
+}
+
+/* Makes the moves it is given, one after the other, then forfeits */
+pub struct ScriptedSnake {
+    directions: Vec<Direction>,
+    next: usize,
+}
+impl ScriptedSnake {
+    pub fn new(directions:&[Direction]) -> ScriptedSnake {
+        ScriptedSnake{directions: directions.to_vec(), next: 0}
+    }
+}
+impl Snake for ScriptedSnake {
+    fn name(&self) -> &'static str { "scripted" }
+    fn init(&mut self, _game:&Game) {
+        self.next = 0;
+    }
+    fn choose_direction(&mut self, _game:&Game) -> Option<Direction> {
+        let dir = self.directions.get(self.next).copied();
+        self.next += 1;
+        dir
+    }
+}
+
+/* How much HumanSnake protects the player from themselves */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Assist {
+    Off,
+    /* a fatal key is ignored if carrying straight on is safe */
+    KeepHeading,
+    /* and failing that any safe move is taken */
+    AnySafeMove,
+}
+
+/* Steered from the keyboard: a/d/w/s or h/l/k/j, followed by Enter as
+ * stdin is line buffered. Without a key it carries on straight.
+ *
+ * Normally the last key typed since the previous move wins. With queued
+ * input it plays like the arcade classic instead: keys are taken in
+ * order, one per move, and keys that would turn straight back or keep
+ * going straight are skipped. */
+pub struct HumanSnake {
+    keys: std::sync::mpsc::Receiver<char>,
+    pending: VecDeque<Direction>,
+    queued: bool,
+    heading: Option<Direction>,
+    assist: Assist,
+}
+impl HumanSnake {
+    pub fn new(assist:Assist) -> HumanSnake {
+        HumanSnake::with_keys(stdin_keys(), assist)
+    }
+    /* Take keys from somewhere else than stdin, a script for instance */
+    pub fn with_keys(keys:std::sync::mpsc::Receiver<char>, assist:Assist) -> HumanSnake {
+        HumanSnake{keys, pending: VecDeque::new(), queued: false, heading: None, assist}
+    }
+    pub fn set_queued_input(&mut self, queued:bool) {
+        self.queued = queued;
+    }
+    fn direction_for(key:char) -> Option<Direction> {
+        match key {
+            'a' | 'h' => Some(Direction::Left),
+            'd' | 'l' => Some(Direction::Right),
+            'w' | 'k' => Some(Direction::Up),
+            's' | 'j' => Some(Direction::Down),
+            _         => None,
+        }
+    }
+}
+impl Snake for HumanSnake {
+    fn name(&self) -> &'static str { "human" }
+    fn is_deterministic(&self) -> bool { false }
+    fn init(&mut self, _game:&Game) {
+        self.heading = None;
+        self.pending.clear();
+    }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let previous = game.last_direction().unwrap_or(Direction::Right);
+        let heading = self.heading.unwrap_or(previous);
+        while let Ok(key) = self.keys.try_recv() {
+            self.pending.extend(HumanSnake::direction_for(key));
+        }
+        let mut chosen = heading;
+        if self.queued {
+            while let Some(dir) = self.pending.pop_front() {
+                if dir != heading && dir != heading.invert() {
+                    chosen = dir;
+                    break;
+                }
+            }
+        } else {
+            chosen = self.pending.back().copied().unwrap_or(heading);
+            self.pending.clear();
+        }
+        if self.assist != Assist::Off && !game.is_legal_move(chosen) {
+            if game.is_legal_move(previous) {
+                chosen = previous;
+            } else if self.assist == Assist::AnySafeMove {
+                chosen = game.safe_moves().into_iter().next().unwrap_or(chosen);
+            }
+        }
+        self.heading = Some(chosen);
+        Some(chosen)
+    }
+}
+
+/* Goes for the apple when a safe move gets it closer, otherwise drifts
+ * towards the middle of the board where it has room in all directions */
+pub struct CenterSeekingSnake;
+impl CenterSeekingSnake {
+    /* Twice the distance to the center, so it works out in whole cells
+     * for boards of even size as well */
+    fn distance_to_center(game:&Game, pos:Coordinate) -> isize {
+        let dimension = game.field.dimension;
+        (2*pos.x - (dimension.x-1)).abs() + (2*pos.y - (dimension.y-1)).abs()
+    }
+}
+impl Snake for CenterSeekingSnake {
+    fn name(&self) -> &'static str { "center-seeking" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let moves = game.legal_directions_toward(game.apple);
+        let target = |dir:&Direction| game.field.move_towards(game.head, *dir);
+        let distance = game.head.manhattan_distance(game.apple);
+        let closer = moves.first().filter(|dir| target(dir).manhattan_distance(game.apple) < distance);
+        closer.or_else(|| moves.iter().min_by_key(|dir| CenterSeekingSnake::distance_to_center(game, target(dir)))).copied()
+    }
+}
+
+/* Heads for the apple, but never along a move that cuts the free space
+ * into separate pockets when there is one that keeps it in one piece,
+ * nor into a space too cramped to hold the snake when it has a choice. */
+pub struct ConnectivitySnake;
+impl Snake for ConnectivitySnake {
+    fn name(&self) -> &'static str { "connectivity" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        game.safe_moves().into_iter().min_by_key(|dir| {
+            let target = game.field.move_towards(game.head, *dir);
+            let (field, length) = ConnectivitySnake::field_after(game, *dir);
+            let cramped = field.longest_open_run(target) < length;
+            (field.free_regions().max(1), cramped, target.manhattan_distance(game.apple))
+        })
+    }
+}
+impl ConnectivitySnake {
+    /* The grid and snake length one step along dir, moving the snake on a
+     * copy of the field only rather than on the whole game */
+    fn field_after(game:&Game, dir:Direction) -> (Field, usize) {
+        let mut field = game.field.clone();
+        let target = field.move_towards(game.head, dir);
+        if field.get_direction_at(target).is_tail() {
+            field.drop_last_in_chain(game.head);
+            field.set_direction_at(target, dir.invert());
+            return (field, game.length);
+        }
+        if field.get_direction_at(target).is_snake() {
+            field.bury(target);
+        }
+        field.set_direction_at(target, dir.invert());
+        if game.is_apple(target) {
+            return (field, game.length + 1);
+        }
+        field.drop_last_in_chain(target);
+        (field, game.length)
+    }
+}
+
+/* Wraps any snake and remembers what it decided and how long it took to
+ * make up its mind, for profiling the expensive ones. */
+pub struct InstrumentedSnake<S: Snake, C: Clock = SystemClock> {
+    snake: S,
+    clock: C,
+    decisions: Vec<(time::Duration, Option<Direction>)>,
+}
+impl<S: Snake> InstrumentedSnake<S> {
+    pub fn new(snake:S) -> InstrumentedSnake<S> {
+        InstrumentedSnake::with_clock(snake, SystemClock::new())
+    }
+}
+impl<S: Snake, C: Clock> InstrumentedSnake<S, C> {
+    pub fn with_clock(snake:S, clock:C) -> InstrumentedSnake<S, C> {
+        InstrumentedSnake{snake, clock, decisions: Vec::new()}
+    }
+    pub fn decisions(&self) -> &[(time::Duration, Option<Direction>)] {
+        &self.decisions
+    }
+    pub fn total_time(&self) -> time::Duration {
+        self.decisions.iter().map(|(duration, _)| *duration).sum()
+    }
+}
+impl<S: Snake, C: Clock> Snake for InstrumentedSnake<S, C> {
+    fn name(&self) -> &'static str { self.snake.name() }
+    fn init(&mut self, game:&Game) {
+        self.decisions.clear();
+        self.snake.init(game);
+    }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let start = self.clock.now();
+        let dir = self.snake.choose_direction(game);
+        self.decisions.push((self.clock.now() - start, dir));
+        dir
+    }
+    fn on_apple_eaten(&mut self, game:&Game) {
+        self.snake.on_apple_eaten(game);
+    }
+    fn cycle(&self, game:&Game) -> Option<Vec<Coordinate>> {
+        self.snake.cycle(game)
+    }
+    fn supports(&self, config:&GameConfig) -> Result<(), String> {
+        self.snake.supports(config)
+    }
+    fn is_deterministic(&self) -> bool {
+        self.snake.is_deterministic()
+    }
+}
+
+/* Wraps any snake and writes a CSV row for every decision it makes: what
+ * the board looked like and which way it went. Meant as a labeled data
+ * set for training snakes. */
+pub struct TrainingDataSnake<S: Snake, W: std::io::Write> {
+    snake: S,
+    out: W,
+    /* the first write that failed, the rest are skipped */
+    error: Option<String>,
+}
+impl<S: Snake, W: std::io::Write> TrainingDataSnake<S, W> {
+    pub const HEADER: &'static str = "head_x,head_y,apple_x,apple_y,blocked_left,blocked_right,blocked_up,blocked_down,reachable,direction";
+    pub fn new(snake:S, out:W) -> TrainingDataSnake<S, W> {
+        let mut exporter = TrainingDataSnake{snake, out, error: None};
+        exporter.write_line(TrainingDataSnake::<S, W>::HEADER);
+        exporter
+    }
+    /* Head and apple position, for every direction whether moving there
+     * would crash, and how many free cells the head can still reach */
+    pub fn features(game:&Game) -> Vec<isize> {
+        let mut features = vec![game.head.x, game.head.y, game.apple.x, game.apple.y];
+        features.extend(Direction::MOVES.iter().map(|dir| !game.is_legal_move(*dir) as isize));
+        features.push(game.field.reachable_count(game.head) as isize);
+        features
+    }
+    fn write_line(&mut self, line:&str) {
+        if self.error.is_none() {
+            self.error = writeln!(self.out, "{}", line).err().map(|err| err.to_string());
+        }
+    }
+    /* Flush and hand back the writer, or the first error writing to it */
+    pub fn finish(mut self) -> Result<W, String> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.out.flush().map_err(|err| err.to_string())?;
+        Ok(self.out)
+    }
+}
+impl<S: Snake, W: std::io::Write> Snake for TrainingDataSnake<S, W> {
+    fn name(&self) -> &'static str { self.snake.name() }
+    fn init(&mut self, game:&Game) {
+        self.snake.init(game);
+    }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        let dir = self.snake.choose_direction(game);
+        /* a forfeit has no label, there is nothing to learn from it */
+        if let Some(dir) = dir {
+            let features = TrainingDataSnake::<S, W>::features(game);
+            let row: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+            self.write_line(&format!("{},{}", row.join(","), dir.to_char()));
+        }
+        dir
+    }
+    fn on_apple_eaten(&mut self, game:&Game) {
+        self.snake.on_apple_eaten(game);
+    }
+    fn cycle(&self, game:&Game) -> Option<Vec<Coordinate>> {
+        self.snake.cycle(game)
+    }
+    fn supports(&self, config:&GameConfig) -> Result<(), String> {
+        self.snake.supports(config)
+    }
+    fn is_deterministic(&self) -> bool {
+        self.snake.is_deterministic()
+    }
+}
+
+/* Heap entry for A*, ordered so the cheapest candidate comes out first */
+struct Candidate {
+    cost: f32,
+    steps: usize,
+    pos: Coordinate,
+}
+impl PartialEq for Candidate {
+    fn eq(&self, other:&Candidate) -> bool {
+        self.cost == other.cost
+    }
+}
+impl Eq for Candidate {}
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other:&Candidate) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Candidate {
+    fn cmp(&self, other:&Candidate) -> std::cmp::Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/* Follows the shortest path to the apple around its body, found with A*.
+ * The heuristic estimates the moves left between two cells and can be
+ * swapped out. Only an admissible heuristic, one that never overestimates
+ * like the default Manhattan distance, guarantees the shortest path. A
+ * weighted one searches fewer cells but may settle for a longer path. */
+pub struct AStarSnake {
+    heuristic: Box<dyn Fn(Coordinate, Coordinate) -> f32>,
+}
+impl Default for AStarSnake {
+    fn default() -> AStarSnake {
+        AStarSnake::new()
+    }
+}
+impl AStarSnake {
+    pub fn new() -> AStarSnake {
+        AStarSnake::with_heuristic(Box::new(|a, b| a.manhattan_distance(b) as f32))
+    }
+    pub fn with_heuristic(heuristic:Box<dyn Fn(Coordinate, Coordinate) -> f32>) -> AStarSnake {
+        AStarSnake{heuristic}
+    }
+    /* Cells from just after from up to and including to */
+    pub fn path(&self, game:&Game, from:Coordinate, to:Coordinate) -> Option<Vec<Coordinate>> {
+        let dimension = game.field.dimension;
+        let mut steps = vec![vec![usize::MAX; dimension.x as usize]; dimension.y as usize];
+        let mut came_from = vec![vec![None; dimension.x as usize]; dimension.y as usize];
+        let mut open = std::collections::BinaryHeap::from([Candidate{cost: 0.0, steps: 0, pos: from}]);
+        steps[from.y as usize][from.x as usize] = 0;
+        while let Some(Candidate{steps: taken, pos, ..}) = open.pop() {
+            if pos == to {
+                let mut path = vec![to];
+                while let Some(previous) = came_from[path[path.len()-1].y as usize][path[path.len()-1].x as usize] {
+                    if previous == from { break; }
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if taken > steps[pos.y as usize][pos.x as usize] {
+                continue; /* already got here cheaper */
+            }
+            for next in pos.neighbors() {
+                if !game.field.coordinate_in_bounds(next) || !game.field.free_at(next) {
+                    continue;
+                }
+                if taken+1 < steps[next.y as usize][next.x as usize] {
+                    steps[next.y as usize][next.x as usize] = taken+1;
+                    came_from[next.y as usize][next.x as usize] = Some(pos);
+                    let cost = (taken+1) as f32 + (self.heuristic)(next, to);
+                    open.push(Candidate{cost, steps: taken+1, pos: next});
+                }
+            }
+        }
+        None
+    }
+}
+impl Snake for AStarSnake {
+    fn name(&self) -> &'static str { "a-star" }
+    fn init(&mut self, _game:&Game) { }
+    fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+        match self.path(game, game.head, game.apple) {
+            Some(path) => game.head.direction_to(path[0]),
+            None       => game.safe_moves().first().copied(), /* stall and hope */
+        }
+    }
+}
+
+// NEXT calculate shortest path and validate with ham snake
+
+/* Only boards with an even side have a Hamiltonian cycle. On odd by odd
+ * boards the Hamiltonian snake reroutes through the top right corner
+ * depending on where the apple is, so there is no fixed path to take
+ * shortcuts along. */
+pub fn has_hamiltonian_cycle(width:usize, height:usize) -> bool {
+    !(odd(width as isize) && odd(height as isize))
+}
+
+/* The cycle the Hamiltonian snake follows, from the top left corner on,
+ * laid out a column at a time: down the first column, up and down the
+ * others below the top row and back along the top. With an odd number of
+ * columns the last two zigzag up together. None if there is no cycle or
+ * a side is shorter than 2. */
+pub fn hamiltonian_cycle(width:usize, height:usize) -> Option<Vec<Coordinate>> {
+    if !has_hamiltonian_cycle(width, height) || width < 2 || height < 2 {
+        return None;
+    }
+    let (w, h) = (width as isize, height as isize);
+    let cell = |x, y| Coordinate{x, y};
+    let mut cycle = Vec::with_capacity(width * height);
+    cycle.extend((0..h).map(|y| cell(0, y)));
+    let snaking = if odd(w) { w - 2 } else { w };
+    for x in 1..snaking {
+        if odd(x) {
+            cycle.extend((1..h).rev().map(|y| cell(x, y)));
+        } else {
+            cycle.extend((1..h).map(|y| cell(x, y)));
+        }
+    }
+    if odd(w) {
+        for y in (1..h).rev() {
+            let pair = [cell(w-2, y), cell(w-1, y)];
+            if odd(h - y) {
+                cycle.extend(pair);
+            } else {
+                cycle.extend(pair.into_iter().rev());
+            }
+        }
+    }
+    cycle.extend((1..w).rev().map(|x| cell(x, 0)));
+    Some(cycle)
+}
+
+/* Does cycle visit every cell of a width by height board exactly once,
+ * each a step from the one before and the last a step from the first? */
+pub fn is_hamiltonian_cycle(cycle:&[Coordinate], width:usize, height:usize) -> bool {
+    let mut seen = vec![vec![false; width]; height];
+    for pos in cycle {
+        if pos.x < 0 || pos.y < 0 || pos.x as usize >= width || pos.y as usize >= height
+            || std::mem::replace(&mut seen[pos.y as usize][pos.x as usize], true) {
+            return false;
+        }
+    }
+    let mut steps = cycle.iter().zip(cycle.iter().cycle().skip(1));
+    cycle.len() == width * height && steps.all(|(a, b)| a.manhattan_distance(*b) == 1)
+}
+
+/* Names of the built in snakes, numbered as in choose_snake() */
+pub const BUILTIN_SNAKES: [&str; 13] = ["silly", "greedy", "greedy-picky", "hamiltonian",
+    "impatient-hamiltonian", "wall-hugger", "a-star", "opportunistic-hamiltonian",
+    "shuffle-bag", "connectivity", "human", "center-seeking", "greedy-avoid-self"];
+
+type SnakeFactory = fn() -> Box<dyn Snake>;
+
+fn snake_registry() -> &'static std::sync::Mutex<Vec<(String, SnakeFactory)>> {
+    static REGISTRY: std::sync::OnceLock<std::sync::Mutex<Vec<(String, SnakeFactory)>>> = std::sync::OnceLock::new();
+    REGISTRY.get_or_init(|| std::sync::Mutex::new(Vec::new()))
+}
+
+/* Make a snake of your own available by name, and to choose_snake() by
+ * number after the built in ones in order of registration */
+pub fn register_snake(name:&str, factory:SnakeFactory) -> Result<(), String> {
+    let mut registry = snake_registry().lock().expect("snake registry poisoned");
+    if BUILTIN_SNAKES.contains(&name) || registry.iter().any(|(known, _)| known == name) {
+        return Err(format!("there already is a snake called {}", name));
+    }
+    registry.push((name.to_string(), factory));
+    Ok(())
+}
+
+/* A built in or registered snake by name */
+pub fn snake_by_name(name:&str, game:&Game) -> Option<Box<dyn Snake>> {
+    if let Some(k) = BUILTIN_SNAKES.iter().position(|builtin| *builtin == name) {
+        return Some(choose_snake(k as u32, game));
+    }
+    let registry = snake_registry().lock().expect("snake registry poisoned");
+    registry.iter().find(|(known, _)| known == name).map(|(_, factory)| factory())
+}
+
+pub fn choose_snake(k:u32, game:&Game) -> Box<dyn Snake> {
+    let (width, height) = (game.field.dimension.x as usize, game.field.dimension.y as usize);
+    match k {
+        4 if !has_hamiltonian_cycle(width, height) => {
+            eprintln!("No Hamiltonian cycle on a {}x{} board, using the plain Hamiltonian snake instead.", width, height);
+            Box::new(HamiltonianSnake::new())
+        }
+        0 => Box::new(SillySnake::new()),
+        1 => Box::new(GreedySnake{}),
+        2 => Box::new(GreedyPickySnake{}),
+        3 => Box::new(HamiltonianSnake::new()),
+        4 => Box::new(ImpatientHamiltonianSnake::new()),
+        5 => Box::new(WallHuggerSnake{}),
+        6 => Box::new(AStarSnake::new()),
+        7 => Box::new(OpportunisticHamiltonianSnake::new()),
+        8 => Box::new(ShuffleBagSnake::new()),
+        9 => Box::new(ConnectivitySnake{}),
+        10 => Box::new(HumanSnake::new(Assist::KeepHeading)),
+        11 => Box::new(CenterSeekingSnake{}),
+        12 => Box::new(GreedyAvoidSelfSnake{}),
+        _ => {
+            let registry = snake_registry().lock().expect("snake registry poisoned");
+            let (_, factory) = registry.get(k as usize - BUILTIN_SNAKES.len()).expect("Never heard of such snake");
+            factory()
+        }
+    }
+}
+
+/* Widest cell, no wider than preferred, that fits a board of
+ * board_width cells on a terminal of columns characters. The row label
+ * and borders take up another 4 characters. */
+pub fn fit_cell_width(preferred:usize, board_width:usize, columns:usize) -> Option<usize> {
+    (1..=preferred.clamp(1, 3)).rev().find(|cell_width| board_width * cell_width + 4 <= columns)
+}
+
+/* Shrink the cells when the terminal is too narrow for the board, grow
+ * them back up to preferred when it allows. */
+fn fit_to_terminal(game:&mut Game, preferred:usize) {
+    let Some((terminal_size::Width(columns), _)) = terminal_size::terminal_size() else {
+        return; /* not a terminal, nothing will wrap */
+    };
+    let width = game.viewport().1.x;
+    match fit_cell_width(preferred, width as usize, columns as usize) {
+        Some(cell_width) => game.theme.cell_width = cell_width,
+        None => {
+            game.theme.cell_width = 1;
+            eprintln!("Terminal too narrow for the board, please widen it to at least {} columns.", width + 4);
+        }
+    }
+}
+
+/* Slow down near walls and body, speed up in the open */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Suspense {
+    /* at this distance from danger or further we go fastest */
+    pub range: usize,
+    pub fastest: time::Duration,
+    pub slowest: time::Duration,
+}
+impl Suspense {
+    pub fn delay(&self, distance:usize) -> time::Duration {
+        if distance >= self.range {
+            return self.fastest;
+        }
+        /* in whole steps rather than as a float, so the ends come out exact */
+        let (closeness, steps) = ((self.range - distance.max(1)) as u32, (self.range - 1).max(1) as u32);
+        self.fastest + self.slowest.saturating_sub(self.fastest) * closeness / steps
+    }
+}
+
+/* Every character typed on stdin, read by a thread of its own so the
+ * game never waits for the keyboard */
+fn stdin_keys() -> std::sync::mpsc::Receiver<char> {
+    let (sender, keys) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        for byte in std::io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            if sender.send(byte as char).is_err() {
+                break;
+            }
+        }
+    });
+    keys
+}
+
+/* Where run() gets the time and how it waits between frames, so the
+ * timing can be checked without waiting for real */
+pub trait Clock {
+    /* time since the clock was made */
+    fn now(&self) -> time::Duration;
+    fn sleep(&mut self, duration:time::Duration);
+}
+
+pub struct SystemClock {
+    start: time::Instant,
+}
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock{start: time::Instant::now()}
+    }
+}
+impl Clock for SystemClock {
+    fn now(&self) -> time::Duration {
+        self.start.elapsed()
+    }
+    fn sleep(&mut self, duration:time::Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/* Time only passes when someone sleeps, and then instantly */
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct MockClock {
+    now: time::Duration,
+}
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock::default()
+    }
+}
+impl Clock for MockClock {
+    fn now(&self) -> time::Duration {
+        self.now
+    }
+    fn sleep(&mut self, duration:time::Duration) {
+        self.now += duration;
+    }
+}
+
+/* Live speed control while watching a game. Keys come in over stdin,
+ * which is line buffered, so each one needs an Enter:
+ *
+ *   +  twice as fast
+ *   -  half as fast
+ *   p  pause or resume
+ */
+pub struct Playback {
+    delay: time::Duration,
+    paused: bool,
+    keys: Option<std::sync::mpsc::Receiver<char>>,
+}
+impl Playback {
+    const FASTEST: time::Duration = time::Duration::from_millis(10);
+    const SLOWEST: time::Duration = time::Duration::from_secs(2);
+    pub fn new(delay:time::Duration) -> Playback {
+        Playback{delay, paused: false, keys: None}
+    }
+    /* Like new() but with keys typed on stdin */
+    pub fn listen(delay:time::Duration) -> Playback {
+        Playback{keys: Some(stdin_keys()), ..Playback::new(delay)}
+    }
+    pub fn key(&mut self, key:char) {
+        match key {
+            '+' => self.delay = (self.delay / 2).max(Playback::FASTEST),
+            '-' => self.delay = (self.delay * 2).min(Playback::SLOWEST),
+            'p' => self.paused = !self.paused,
+            _   => (),
+        }
+    }
+    pub fn delay(&self) -> time::Duration {
+        self.delay
+    }
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /* Sleep until the next frame is due, and for as long as paused */
+    pub fn wait(&mut self, clock:&mut dyn Clock) {
+        loop {
+            while let Some(key) = self.keys.as_ref().and_then(|keys| keys.try_recv().ok()) {
+                self.key(key);
+            }
+            if !self.paused {
+                break;
+            }
+            clock.sleep(Playback::FASTEST);
+        }
+        clock.sleep(self.delay);
+    }
+}
+
+/* Events that pause a drawn game, to have a good look at what a snake
+ * is up to */
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Breakpoints {
+    pub apple_eaten: bool,
+    /* when the snake grows to this length */
+    pub length: Option<usize>,
+    /* when the free cells the head can reach drop below this */
+    pub reachable_below: Option<usize>,
+}
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Breakpoint {
+    AppleEaten,
+    Length(usize),
+    ReachableBelow(usize),
+}
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Breakpoint::AppleEaten         => write!(f, "apple eaten"),
+            Breakpoint::Length(length)     => write!(f, "snake is {} long", length),
+            Breakpoint::ReachableBelow(n)  => write!(f, "fewer than {} reachable cells", n),
+        }
+    }
+}
+impl Breakpoints {
+    /* Which breakpoint, if any, the step that ended in outcome hit. Given
+     * how many cells were reachable before it, so the reachable space only
+     * triggers when it drops below the limit, not on every step after. */
+    pub fn check(&self, game:&Game, outcome:StepOutcome, reachable_before:usize) -> Option<Breakpoint> {
+        let ate = outcome == StepOutcome::AteApple || outcome == StepOutcome::Won;
+        if self.apple_eaten && ate {
+            return Some(Breakpoint::AppleEaten);
+        }
+        if let Some(length) = self.length.filter(|length| ate && game.length == *length) {
+            return Some(Breakpoint::Length(length));
+        }
+        if let Some(limit) = self.reachable_below {
+            if reachable_before >= limit && game.field.reachable_count(game.head) < limit {
+                return Some(Breakpoint::ReachableBelow(limit));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    pub draw: bool,
+    pub delay: time::Duration,
+    /* overrides delay when set */
+    pub suspense: Option<Suspense>,
+    /* Stop a snake that goes around in circles forever */
+    pub max_moves: Option<u32>,
+    /* Stop once this many apples are eaten */
+    pub apple_target: Option<u32>,
+    /* Take speed and pause keys from stdin while drawing, see Playback */
+    pub playback: bool,
+    /* only checked while drawing */
+    pub breakpoints: Breakpoints,
+    /* Keep going after a win, until the snake dies or runs out of moves.
+     * On a full board it just chases its tail, so set max_moves. */
+    pub continue_after_win: bool,
+    /* Print a MoveRecord line to stdout after every move */
+    pub stream_json: bool,
+    /* The snake goes faster as it grows: one cell per frame for every
+     * this many segments, rounded up, all in the direction it picked at
+     * the start of the frame. */
+    pub length_speed: Option<NonZeroUsize>,
+}
+impl Default for RunOptions {
+    fn default() -> RunOptions {
+        RunOptions{
+            draw: false,
+            delay: time::Duration::ZERO,
+            suspense: None,
+            max_moves: None,
+            apple_target: None,
+            playback: false,
+            breakpoints: Breakpoints::default(),
+            continue_after_win: false,
+            stream_json: false,
+            length_speed: None,
+        }
+    }
+}
+
+/* Why a game stopped. Finished holds the outcome of the final step. */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameEnd {
+    Finished(StepOutcome),
+    Forfeit,
+    OutOfMoves,
+    ReachedAppleTarget,
+}
+
+/* How a snake can lose. Display gives the message run() prints. */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DeathReason {
+    Forfeit,
+    Gibberish,
+    CrashedInWall,
+    AteSnake,
+}
+impl std::fmt::Display for DeathReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DeathReason::Forfeit       => write!(f, "Snake forfeit."),
+            DeathReason::Gibberish     => write!(f, "Snake is ejected because it speaks gibberish."),
+            DeathReason::CrashedInWall => write!(f, "crashed in wall."),
+            DeathReason::AteSnake      => write!(f, "ate snake"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GameResult {
+    pub end: GameEnd,
+    pub apples: u32,
+    pub moves: u32,
+    pub head: Coordinate,
+    /* moves it took to win, for games that went on after */
+    pub won_at: Option<u32>,
+    /* see Game::path_efficiency() */
+    pub path_efficiency: Option<f32>,
+}
+impl GameResult {
+    pub fn won(&self) -> bool {
+        self.end == GameEnd::Finished(StepOutcome::Won) || self.won_at.is_some()
+    }
+    /* Moves the snake stayed alive after winning */
+    pub fn survived_after_win(&self) -> Option<u32> {
+        self.won_at.map(|won_at| self.moves - won_at)
+    }
+    pub fn died(&self) -> bool {
+        self.death_reason().is_some()
+    }
+    pub fn death_reason(&self) -> Option<DeathReason> {
+        match self.end {
+            GameEnd::Forfeit                                 => Some(DeathReason::Forfeit),
+            GameEnd::Finished(StepOutcome::Gibberish)        => Some(DeathReason::Gibberish),
+            GameEnd::Finished(StepOutcome::CrashedInWall)    => Some(DeathReason::CrashedInWall),
+            GameEnd::Finished(StepOutcome::AteSnake)         => Some(DeathReason::AteSnake),
+            GameEnd::Finished(_) | GameEnd::OutOfMoves | GameEnd::ReachedAppleTarget => None,
+        }
+    }
+}
+
+/* Let snake play game until it wins, dies or runs out of moves */
+pub fn run(game:&mut Game, snake:&mut dyn Snake, options:&RunOptions) -> GameResult {
+    run_with_clock(game, snake, options, &mut SystemClock::new())
+}
+
+/* run() with all waiting between frames done on clock */
+pub fn run_with_clock(game:&mut Game, snake:&mut dyn Snake, options:&RunOptions, clock:&mut dyn Clock) -> GameResult {
+    snake.init(game);
+    let cell_width = game.theme.cell_width();
+    let mut playback = (options.draw && options.playback).then(|| Playback::listen(options.delay));
+    if options.draw && game.theme.show_cycle {
+        game.set_cycle_overlay(snake.cycle(game).as_deref());
+    }
+    if options.draw {
+        fit_to_terminal(game, cell_width);
+        game.draw();
+    }
+    let mut won_at = None;
+    let end = loop {
+        if options.max_moves.is_some_and(|max| game.moves >= max) {
+            break GameEnd::OutOfMoves;
+        }
+        if options.apple_target.is_some_and(|target| game.apples >= target) {
+            break GameEnd::ReachedAppleTarget;
+        }
+        let Some(snake_dir) = snake.choose_direction(game) else {
+            break GameEnd::Forfeit;
+        };
+        let reachable = match options.breakpoints.reachable_below {
+            Some(_) if options.draw => game.field.reachable_count(game.head),
+            _                       => 0,
+        };
+        let cells = options.length_speed.map_or(1, |segments| game.length.div_ceil(segments.get()));
+        let mut outcome = StepOutcome::Moved;
+        for _ in 0..cells {
+            outcome = game.step(snake_dir);
+            if outcome == StepOutcome::AteApple || outcome == StepOutcome::Won {
+                snake.on_apple_eaten(game);
+            }
+            if options.stream_json {
+                println!("{}", MoveRecord::new(game, outcome).to_json());
+            }
+            if outcome != StepOutcome::Moved && outcome != StepOutcome::AteApple {
+                break;
+            }
+        }
+        if outcome == StepOutcome::Won && options.continue_after_win {
+            won_at = won_at.or(Some(game.moves));
+        } else if outcome != StepOutcome::Moved && outcome != StepOutcome::AteApple {
+            break GameEnd::Finished(outcome);
+        }
+        if options.draw {
+            match (&mut playback, options.suspense) {
+                (Some(playback), _)    => playback.wait(clock),
+                (None, Some(suspense)) => clock.sleep(suspense.delay(game.distance_to_danger())),
+                (None, None)           => clock.sleep(options.delay),
+            }
+            print!("{}[2J", 27 as char); //Clear screen
+            fit_to_terminal(game, cell_width);
+            game.draw();
+            if let Some(hit) = options.breakpoints.check(game, outcome, reachable) {
+                match &mut playback {
+                    Some(playback) => {
+                        println!("Breakpoint: {}. Paused, p to resume.", hit);
+                        playback.pause();
+                    }
+                    None => {
+                        println!("Breakpoint: {}. Press Enter to resume.", hit);
+                        let _ = std::io::stdin().read_line(&mut String::new());
+                    }
+                }
+            }
+        }
+    };
+    let result = GameResult{end, apples: game.apples, moves: game.moves, head: game.head, won_at, path_efficiency: game.path_efficiency()};
+    if options.draw {
+        if let Some(reason) = result.death_reason() {
+            println!("{}", reason);
+        } else if result.won() {
+            println!("The Snake has won the game.");
+        }
+        game.draw();
+        game.theme.cell_width = cell_width;
+    }
+    result
+}
+
+/* Watch a recorded game, with keys to change the speed or pause */
+pub fn replay(replay:&Replay) -> GameResult {
+    let mut game = Game::init_seeded(replay.width, replay.height, replay.seed);
+    let mut snake = ScriptedSnake::new(&replay.directions);
+    let options = RunOptions{draw: true, delay: time::Duration::from_millis(300), playback: true, ..RunOptions::default()};
+    run(&mut game, &mut snake, &options)
+}
+
+/* Play until the snake has eaten target apples, or until it dies before */
+pub fn run_until_apples(game:&mut Game, snake:&mut dyn Snake, target:u32, options:&RunOptions) -> GameResult {
+    run(game, snake, &RunOptions{apple_target: Some(target), ..options.clone()})
+}
+
+/* Watch the game for seed play out slowly, to see how a snake got itself
+ * killed in a headless run. */
+pub fn reproduce(seed:u64, width:usize, height:usize, snake:&mut dyn Snake) -> GameResult {
+    warn_if_nondeterministic(snake);
+    let options = RunOptions{draw: true, delay: time::Duration::from_millis(300), ..RunOptions::default()};
+    run(&mut Game::init_seeded(width, height, seed), snake, &options)
+}
+
+/* Totals for one snake over a series of games */
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Tally {
+    pub games: u32,
+    pub wins: u32,
+    pub deaths: u32,
+    pub apples: u32,
+    pub moves: u32,
+}
+impl Tally {
+    pub fn add(&mut self, result:&GameResult) {
+        self.games += 1;
+        self.wins += result.won() as u32;
+        self.deaths += result.died() as u32;
+        self.apples += result.apples;
+        self.moves += result.moves;
+    }
+    pub fn win_rate(&self) -> f32 {
+        self.wins as f32 / self.games as f32
+    }
+    pub fn survival_rate(&self) -> f32 {
+        1.0 - self.deaths as f32 / self.games as f32
+    }
+    pub fn apples_per_game(&self) -> f32 {
+        self.apples as f32 / self.games as f32
+    }
+    pub fn moves_per_apple(&self) -> f32 {
+        self.moves as f32 / self.apples as f32
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Winner {
+    A,
+    B,
+    Tie,
+}
+impl Winner {
+    pub fn higher(a:f32, b:f32) -> Winner {
+        if a > b { Winner::A } else if b > a { Winner::B } else { Winner::Tie }
+    }
+    pub fn lower(a:f32, b:f32) -> Winner {
+        Winner::higher(b, a)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Comparison {
+    pub names: (&'static str, &'static str),
+    pub a: Tally,
+    pub b: Tally,
+    pub win_rate: Winner,
+    pub survival: Winner,
+    pub apples: Winner,
+    pub efficiency: Winner,
+    pub overall: Winner,
+}
+
+/* Plenty for a Hamiltonian snake to fill the board, which takes about
+ * area²/4 moves. Anything slower is considered stuck. */
+pub fn move_budget(width:usize, height:usize) -> u32 {
+    let area = width.saturating_mul(height) as u64;
+    area.saturating_mul(area).min(u32::MAX as u64) as u32
+}
+
+/* Can snake fill the board for this seed within the move budget? */
+pub fn is_winnable(width:usize, height:usize, seed:u64, snake:&mut dyn Snake) -> bool {
+    let options = RunOptions{max_moves: Some(move_budget(width, height)), ..RunOptions::default()};
+    run(&mut Game::init_seeded(width, height, seed), snake, &options).won()
+}
+
+/* The first seed in seeds on which snake dies, to have a case to
+ * reproduce() when hunting a bug */
+pub fn find_failing_seed(snake:&mut dyn Snake, width:usize, height:usize, seeds:std::ops::Range<u64>) -> Option<u64> {
+    warn_if_nondeterministic(snake);
+    let options = RunOptions{max_moves: Some(move_budget(width, height)), ..RunOptions::default()};
+    seeds.into_iter().find(|seed| run(&mut Game::init_seeded(width, height, *seed), snake, &options).died())
+}
+
+/* For tests of a snake's behaviour: play the game for seed and panic
+ * unless the moves the snake makes are exactly expected, showing both as
+ * replay letters with a marker under the first difference. */
+pub fn assert_replay_matches(seed:u64, width:usize, height:usize, snake:&mut dyn Snake, expected:&[Direction]) {
+    warn_if_nondeterministic(snake);
+    let mut game = Game::init_seeded(width, height, seed);
+    run(&mut game, snake, &RunOptions{max_moves: Some(expected.len() as u32), ..RunOptions::default()});
+    let mut moves = game.record(snake.name()).expect("a seeded game has plain rules").directions;
+    moves.truncate(game.moves as usize);
+    if moves == expected {
+        return;
+    }
+    let letters = |dirs:&[Direction]| dirs.iter().map(|dir| dir.to_char()).collect::<String>();
+    let first = moves.iter().zip(expected).take_while(|(made, wanted)| made == wanted).count();
+    panic!("The {} snake went its own way after {} moves on seed {} ({}x{}):\nexpected: {}\nactual:   {}\n          {}^",
+        snake.name(), first, seed, width, height, letters(expected), letters(&moves), " ".repeat(first));
+}
+
+/* Play both snakes on the very same boards and seeds and see which one
+ * does better. */
+pub fn compare(snake_a:&mut dyn Snake, snake_b:&mut dyn Snake, board_sizes:&[(usize, usize)], seeds:&[u64]) -> Comparison {
+    let mut a = Tally::default();
+    let mut b = Tally::default();
+    for &(width, height) in board_sizes {
+        let options = RunOptions{max_moves: Some(move_budget(width, height)), ..RunOptions::default()};
+        for &seed in seeds {
+            a.add(&run(&mut Game::init_seeded(width, height, seed), snake_a, &options));
+            b.add(&run(&mut Game::init_seeded(width, height, seed), snake_b, &options));
+        }
+    }
+    let metrics = [
+        Winner::higher(a.win_rate(), b.win_rate()),
+        Winner::higher(a.survival_rate(), b.survival_rate()),
+        Winner::higher(a.apples_per_game(), b.apples_per_game()),
+        Winner::lower(a.moves_per_apple(), b.moves_per_apple()),
+    ];
+    let score_a = metrics.iter().filter(|w| **w == Winner::A).count();
+    let score_b = metrics.iter().filter(|w| **w == Winner::B).count();
+    Comparison{
+        names: (snake_a.name(), snake_b.name()),
+        a,
+        b,
+        win_rate: metrics[0],
+        survival: metrics[1],
+        apples: metrics[2],
+        efficiency: metrics[3],
+        overall: Winner::higher(score_a as f32, score_b as f32),
+    }
+}
+
+/* How fast a snake got through a board of one size */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SweepTiming {
+    pub width: usize,
+    pub height: usize,
+    pub moves: u32,
+    pub elapsed: time::Duration,
+}
+impl SweepTiming {
+    pub fn moves_per_second(&self) -> f64 {
+        self.moves as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/* Time snake headless on every board size, at most max_moves moves
+ * each. Moves per second dropping as the boards grow shows what the
+ * per-move cost scales with. */
+pub fn sweep(snake:&mut dyn Snake, board_sizes:&[(usize, usize)], max_moves:u32) -> Vec<SweepTiming> {
+    sweep_with_clock(snake, board_sizes, max_moves, &mut SystemClock::new())
+}
+
+pub fn sweep_with_clock(snake:&mut dyn Snake, board_sizes:&[(usize, usize)], max_moves:u32, clock:&mut dyn Clock) -> Vec<SweepTiming> {
+    let options = RunOptions{max_moves: Some(max_moves), ..RunOptions::default()};
+    board_sizes.iter().map(|&(width, height)| {
+        let mut game = Game::init(width, height);
+        let start = clock.now();
+        let result = run_with_clock(&mut game, snake, &options, clock);
+        SweepTiming{width, height, moves: result.moves, elapsed: clock.now() - start}
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x:isize, y:isize) -> Coordinate {
+        Coordinate{x, y}
+    }
+
+    impl Game {
+        /* Put the snake down on segments, head first, as if it had just
+         * crawled there */
+        fn force_body(&mut self, segments:&[Coordinate]) {
+            self.field.directions.iter_mut().flatten().filter(|cell| cell.is_snake()).for_each(|cell| *cell = Direction::Null);
+            self.field.place_body(segments).unwrap();
+            self.head = segments[0];
+            self.length = segments.len();
+            self.last_direction = segments.get(1).and_then(|neck| neck.direction_to(segments[0]));
+        }
+    }
+
+    /* Make the moves as a snake would, checking every step against what
+     * collision() said of it beforehand and the field after */
+    fn force_transitions(game:&mut Game, moves:&[Direction]) -> Vec<StepOutcome> {
+        let mut snake = ScriptedSnake::new(moves);
+        let mut outcomes = Vec::new();
+        while let Some(dir) = snake.choose_direction(game) {
+            let predicted = game.collision(dir);
+            let outcome = game.step(dir);
+            assert_eq!(predicted, (!outcome.committed()).then_some(outcome), "{:?} from {}", dir, game.head());
+            game.check_invariants();
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    #[test]
+    fn following_the_tail_overwrites_it_without_eating_the_snake() {
+        /* a ring around a 2x2 block, the head right next to the tail */
+        let mut game = Game::init_with_positions(3, 2, at(0, 0), at(2, 0)).unwrap();
+        game.force_body(&[at(0, 0), at(1, 0), at(1, 1), at(0, 1)]);
+        let lap = [Direction::Down, Direction::Right, Direction::Up, Direction::Left];
+        let outcomes = force_transitions(&mut game, &[lap, lap].concat());
+        assert_eq!(outcomes, vec![StepOutcome::Moved; 8]);
+        assert_eq!(game.body(), vec![at(0, 0), at(1, 0), at(1, 1), at(0, 1)]);
+    }
+
+    #[test]
+    fn moving_into_the_body_behind_the_tail_is_eating_the_snake() {
+        let mut game = Game::init_with_positions(3, 3, at(0, 0), at(2, 2)).unwrap();
+        game.force_body(&[at(0, 0), at(1, 0), at(1, 1), at(0, 1), at(0, 2)]);
+        assert_eq!(force_transitions(&mut game, &[Direction::Down]), vec![StepOutcome::AteSnake]);
+        assert_eq!(game.head(), at(0, 0));
+    }
+
+    #[test]
+    fn wrapping_edges_are_drawn_as_seams() {
+        let mut game = Game::init_with_positions(4, 3, at(0, 0), at(3, 2)).unwrap();
+        let frame = game.render();
+        let lines: Vec<&str> = frame.lines().collect();
+        assert!(lines[1].contains('━') && !lines[1].contains('┅'));
+        assert!(lines[2].contains('┃') && !lines[2].contains('┇'));
+
+        game.set_edges(EdgeModes{top: EdgeMode::Wrap, bottom: EdgeMode::Wrap, left: EdgeMode::Wall, right: EdgeMode::Wrap});
+        let frame = game.render();
+        let lines: Vec<&str> = frame.lines().collect();
+        assert!(lines[1].contains('┅') && !lines[1].contains('━'));
+        assert!(lines[2].starts_with("0 ┃") && lines[2].ends_with('┇'));
+        assert!(lines[5].contains('┅'));
+    }
+
+    #[test]
+    fn body_follows_the_head_over_a_one_way_wrapping_edge() {
+        let mut game = Game::init_with_positions(4, 2, at(1, 0), at(3, 0)).unwrap();
+        game.set_edges(EdgeModes{right: EdgeMode::Wrap, ..EdgeModes::default()});
+        game.set_debug_invariants(true);
+        for _ in 0..3 {
+            assert!(game.step(Direction::Right).committed());
+        }
+        assert_eq!(game.head(), at(0, 0));
+        assert_eq!(game.body()[..2], [at(0, 0), at(3, 0)]);
+    }
+
+    #[test]
+    fn teleport_into_a_full_board_keeps_the_length() {
+        let mut game = Game::init_with_positions(2, 1, at(0, 0), at(1, 0)).unwrap();
+        game.set_mode(GameMode::Teleport);
+        assert_eq!(game.step(Direction::Right), StepOutcome::Won);
+        assert_eq!(game.length(), 2);
+        game.check_invariants();
+    }
+
+    /* Throw random moves, sensible or not, at games of random size and rules
+     * with check_invariants() after every step. Deterministic, so a failing
+     * run can be repeated. */
+    #[test]
+    fn fuzz_step() {
+        let (games, moves) = (2000, 300);
+        const ALL: [Direction; 7] = [Direction::Left, Direction::Right, Direction::Up, Direction::Down,
+                                     Direction::End, Direction::Null, Direction::Wall];
+        /* games are played on another thread, so one where step() never
+         * returns fails here instead of stalling the test run */
+        let (played, progress) = std::sync::mpsc::channel();
+        let fuzzer = std::thread::spawn(move || {
+            let mut rng = StdRng::seed_from_u64(0);
+            let edge = |rng:&mut StdRng| if rng.gen_bool(0.3) { EdgeMode::Wrap } else { EdgeMode::Wall };
+            for seed in 0..games {
+                /* down to boards one cell across, but at least two cells */
+                let width = rng.gen_range(1..=8);
+                let height = rng.gen_range(if width == 1 { 2 } else { 1 }..=8);
+                let mut game = Game::init_seeded(width, height, seed);
+                game.set_debug_invariants(true);
+                game.set_edges(EdgeModes{top: edge(&mut rng), bottom: edge(&mut rng), left: edge(&mut rng), right: edge(&mut rng)});
+                match rng.gen_range(0..10) {
+                    0 | 1 => game.set_mode(GameMode::Teleport),
+                    2     => game.set_mode(GameMode::Ghost),
+                    _     => (),
+                }
+                if rng.gen_bool(0.3) {
+                    let mut cell = || at(rng.gen_range(0..width as isize), rng.gen_range(0..height as isize));
+                    let (a, b) = (cell(), cell());
+                    /* not every pair makes a portal, those are just left out */
+                    let _ = game.add_portal(a, b);
+                }
+                game.set_apple_count(rng.gen_range(1..=3));
+                for _ in 0..moves {
+                    /* mostly real moves, so the snake gets somewhere */
+                    let dir = if rng.gen_bool(0.9) { Direction::random(&mut rng) } else { ALL[rng.gen_range(0..ALL.len())] };
+                    if game.step(dir) == StepOutcome::Won {
+                        break;
+                    }
+                }
+                played.send(seed).unwrap();
+            }
+        });
+        for seed in 0..games {
+            match progress.recv_timeout(time::Duration::from_secs(10)) {
+                Ok(done) => assert_eq!(done, seed),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => panic!("step() hangs in game {}", seed),
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        if let Err(panic) = fuzzer.join() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    #[test]
+    fn config_round_trips_through_json_into_identical_games() {
+        let config = GameConfig{width: 7, height: 6, seed: 9, mode: GameMode::Teleport, wall_density: 0.2, ..GameConfig::default()};
+        let parsed = GameConfig::from_json(&config.to_json()).unwrap();
+        assert_eq!(parsed, config);
+        let mut a = Game::from_config(&config).unwrap();
+        let mut b = Game::from_config(&parsed).unwrap();
+        for dir in [Direction::Up, Direction::Left, Direction::Down, Direction::Down] {
+            assert_eq!(a.step(dir), b.step(dir));
+        }
+        assert_eq!((a.head(), a.apple(), a.render()), (b.head(), b.apple(), b.render()));
+    }
+
+    #[test]
+    fn config_without_cells_is_refused() {
+        assert!(Game::from_config(&GameConfig{width: 0, ..GameConfig::default()}).is_err());
+        assert!(Game::from_config(&GameConfig{height: 0, ..GameConfig::default()}).is_err());
+    }
+
+    #[test]
+    fn only_plain_games_are_recorded() {
+        let mut game = Game::from_config(&GameConfig::default()).unwrap();
+        game.step(Direction::Up);
+        assert!(game.record("greedy").is_ok());
+        let config = GameConfig{edges: EdgeModes{top: EdgeMode::Wrap, ..EdgeModes::default()}, ..GameConfig::default()};
+        assert!(Game::from_config(&config).unwrap().record("greedy").is_err());
+        /* a replay would start from the seeded head and apple instead */
+        assert!(Game::init_with_positions(6, 6, at(5, 5), at(0, 0)).unwrap().record("greedy").is_err());
+    }
+
+    #[test]
+    fn level_apple_that_despawns_comes_back_in_place() {
+        let level = Level::from_text("#######\n#@.1.2#\n#.....#\n#######\n").unwrap();
+        let mut game = Game::from_level(&level).unwrap();
+        game.set_apple_lifetime(Some(3), 1);
+        for dir in [Direction::Down, Direction::Up, Direction::Right] {
+            assert_eq!(game.step(dir), StepOutcome::Moved);
+        }
+        assert_eq!(game.missed_apples(), 1);
+        assert_eq!(game.apple(), at(3, 1));
+        assert_eq!(game.apples_left_in_sequence(), 1);
+        assert_eq!(game.step(Direction::Right), StepOutcome::AteApple);
+        assert_eq!(game.apple(), at(5, 1));
+        assert_eq!(game.step(Direction::Right), StepOutcome::Moved);
+        assert_eq!(game.step(Direction::Right), StepOutcome::Won);
+    }
+
+    fn three_long_snake(width:usize) -> Game {
+        let mut game = Game::init_with_positions(width, 3, at(2, 1), at(0, 0)).unwrap();
+        game.field.place_body(&[at(2, 1), at(1, 1), at(0, 1)]).unwrap();
+        game.length = 3;
+        game
+    }
+
+    #[test]
+    fn long_snake_moves_several_cells_per_frame() {
+        let options = RunOptions{length_speed: NonZeroUsize::new(2), ..RunOptions::default()};
+        let mut game = three_long_snake(10);
+        run(&mut game, &mut ScriptedSnake::new(&[Direction::Right]), &options);
+        assert_eq!((game.head(), game.moves()), (at(4, 1), 2));
+
+        /* the wall is checked on every cell, not just where the frame ends */
+        let mut game = three_long_snake(4);
+        let result = run(&mut game, &mut ScriptedSnake::new(&[Direction::Right]), &options);
+        assert_eq!(result.end, GameEnd::Finished(StepOutcome::CrashedInWall));
+        assert_eq!((game.head(), game.moves()), (at(3, 1), 1));
+    }
+
+    #[test]
+    fn pulled_apple_keeps_path_efficiency_at_least_one() {
+        let mut game = Game::init_with_positions(9, 1, at(0, 0), at(8, 0)).unwrap();
+        game.set_apple_magnet(Some(1));
+        while game.step(Direction::Right) != StepOutcome::AteApple {}
+        assert_eq!(game.path_efficiency(), Some(1.0));
+    }
+
+    #[test]
+    fn lone_head_may_turn_back_but_a_snake_with_a_neck_may_not() {
+        for dir in Direction::MOVES {
+            let mut game = Game::init_with_positions(3, 3, at(1, 1), at(0, 0)).unwrap();
+            game.set_initial_heading(Direction::Right).unwrap();
+            assert!(game.is_legal_move(dir), "{:?}", dir);
+        }
+        /* head, then the apple just eaten, the neck is also the tail */
+        let mut game = Game::init_with_positions(4, 1, at(1, 0), at(2, 0)).unwrap();
+        assert_eq!(game.step(Direction::Right), StepOutcome::AteApple);
+        assert_eq!(game.length(), 2);
+        assert_eq!(game.step(Direction::Left), StepOutcome::AteSnake);
+        assert_eq!(game.head(), at(2, 0));
+    }
+
+    #[test]
+    fn cycle_for_a_50_by_50_board_is_hamiltonian_and_quick() {
+        let start = std::time::Instant::now();
+        let cycle = hamiltonian_cycle(50, 50).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert!(is_hamiltonian_cycle(&cycle, 50, 50));
+    }
+
+    #[test]
+    fn hamiltonian_snake_follows_the_cycle() {
+        for (width, height) in [(4, 4), (5, 4), (4, 5), (6, 3)] {
+            let cycle = hamiltonian_cycle(width, height).unwrap();
+            let game = Game::init_with_positions(width, height, cycle[0], cycle[2]).unwrap();
+            let mut snake = HamiltonianSnake::new();
+            snake.init(&game);
+            for (i, pos) in cycle.iter().enumerate() {
+                let next = cycle[(i+1) % cycle.len()];
+                assert_eq!(Some(snake.direction_from(&game, *pos, game.apple())), pos.direction_to(next));
+            }
+        }
+    }
+
+    #[test]
+    fn move_budget_saturates_on_large_boards() {
+        assert_eq!(move_budget(4, 4), 256);
+        assert_eq!(move_budget(255, 255), 65025*65025);
+        assert_eq!(move_budget(256, 256), u32::MAX);
+        assert_eq!(move_budget(1000, 1000), u32::MAX);
+    }
+
+    #[test]
+    fn rows_and_borders_scale_with_cell_width() {
+        let mut game = Game::init_with_positions(4, 3, at(0, 0), at(3, 2)).unwrap();
+        for (cell_width, drawn) in [(0, 1), (1, 1), (2, 2), (3, 3), (5, 3)] {
+            game.set_theme(Theme{cell_width, ..Theme::default()});
+            let frame = game.render();
+            let widths: Vec<usize> = frame.lines().skip(1).take(5).map(|line| line.chars().count()).collect();
+            assert_eq!(widths, vec![4 + 4*drawn; 5], "cell_width {}", cell_width);
+        }
+        assert_eq!(fit_cell_width(0, 10, 80), Some(1));
+        assert_eq!(fit_cell_width(5, 10, 80), Some(3));
+    }
+
+    #[test]
+    fn perf_counters_grow_with_the_board_and_are_zero_when_off() {
+        let counters = |size| {
+            let game = Game::init_seeded(size, size, 1);
+            game.path_distance_to_apple();
+            game.perf_counters()
+        };
+        let (small, large) = (counters(4), counters(12));
+        if cfg!(feature = "perf") {
+            assert!(large.reads > small.reads && small.reads > 0);
+        } else {
+            assert_eq!((small, large), (PerfCounters::default(), PerfCounters::default()));
+        }
+    }
+
+    #[test]
+    fn trail_keeps_only_the_most_recent_heads() {
+        let mut game = Game::init_seeded(8, 8, 3);
+        game.set_trail_length(5);
+        run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(50), ..RunOptions::default()});
+        assert_eq!(game.trail.len(), 5);
+        game.set_trail_length(2);
+        assert_eq!(game.trail.len(), 2);
+    }
+
+    #[test]
+    fn two_by_three_board_is_filled_for_a_win() {
+        let mut game = Game::init_seeded(2, 3, 5);
+        let result = run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(move_budget(2, 3)), ..RunOptions::default()});
+        assert!(result.won());
+        assert_eq!(game.length(), 6);
+        assert_eq!(game.free_cells(), 0);
+        game.check_invariants();
+    }
+
+    #[test]
+    fn wall_makes_the_path_to_the_apple_longer_than_manhattan() {
+        let mut game = Game::init_with_positions(5, 3, at(0, 1), at(2, 1)).unwrap();
+        assert_eq!(game.path_distance_to_apple(), Some(2));
+        game.field.set_direction_at(at(1, 0), Direction::Wall);
+        game.field.set_direction_at(at(1, 1), Direction::Wall);
+        assert_eq!(game.path_distance_to_apple(), Some(4));
+        assert!(game.path_distance_to_apple().unwrap() > game.head().manhattan_distance(game.apple()) as usize);
+    }
+
+    #[test]
+    fn last_direction_follows_committed_moves() {
+        let mut game = Game::init_with_positions(4, 4, at(1, 1), at(3, 3)).unwrap();
+        assert_eq!(game.last_direction(), None);
+        game.step(Direction::Right);
+        assert_eq!(game.last_direction(), Some(Direction::Right));
+        game.step(Direction::Down);
+        assert_eq!(game.last_direction(), Some(Direction::Down));
+        /* a crash is not committed */
+        game.step(Direction::Wall);
+        assert_eq!(game.last_direction(), Some(Direction::Down));
+    }
+
+    #[test]
+    fn border_none_draws_no_lines_and_double_draws_double_ones() {
+        let mut game = Game::init_with_positions(4, 3, at(0, 0), at(3, 2)).unwrap();
+        let lines = |style:BorderStyle| style.glyphs().into_iter().collect::<String>();
+        let (single, double, heavy) = (lines(BorderStyle::Single), lines(BorderStyle::Double), lines(BorderStyle::Heavy));
+        game.set_theme(Theme{border: BorderStyle::None, ..Theme::default()});
+        let frame = game.render();
+        assert!(!frame.chars().any(|c| single.contains(c) || double.contains(c) || heavy.contains(c)));
+        game.set_theme(Theme{border: BorderStyle::Double, ..Theme::default()});
+        let frame = game.render();
+        assert!(double.chars().all(|c| frame.contains(c)));
+    }
+
+    #[test]
+    fn wall_hugger_stays_on_the_perimeter_with_the_apple_in_the_middle() {
+        let mut game = Game::init_with_positions(5, 5, at(0, 2), at(2, 2)).unwrap();
+        game.force_body(&[at(0, 2), at(0, 1), at(0, 0)]);
+        let mut snake = WallHuggerSnake{};
+        /* down, round the corner and along the bottom edge */
+        for _ in 0..6 {
+            let dir = snake.choose_direction(&game).unwrap();
+            assert_eq!(game.step(dir), StepOutcome::Moved);
+            assert!(game.field().is_border(game.head()), "left the wall for {}", game.head());
+        }
+    }
+
+    #[test]
+    fn hamiltonian_beats_greedy_on_win_rate() {
+        let comparison = compare(&mut GreedySnake{}, &mut HamiltonianSnake::new(), &[(4, 4), (6, 5)], &[1, 2, 3]);
+        assert_eq!(comparison.names, ("greedy", "hamiltonian"));
+        assert_eq!(comparison.win_rate, Winner::B);
+        assert_eq!(comparison.b.win_rate(), 1.0);
+    }
+
+    #[test]
+    fn reproduce_dies_where_the_headless_run_did() {
+        let headless = run(&mut Game::init_seeded(4, 4, 0), &mut GreedySnake{}, &RunOptions::default());
+        assert!(headless.died());
+        let watched = reproduce(0, 4, 4, &mut GreedySnake{});
+        assert_eq!((watched.end, watched.head, watched.moves), (headless.end, headless.head, headless.moves));
+    }
+
+    #[test]
+    fn instrumented_snake_records_one_decision_per_move() {
+        let mut snake = InstrumentedSnake::new(GreedyPickySnake{});
+        let mut game = Game::init_seeded(6, 6, 4);
+        let result = run(&mut game, &mut snake, &RunOptions{max_moves: Some(12), ..RunOptions::default()});
+        assert_eq!(snake.decisions().len(), result.moves as usize);
+        assert_eq!(snake.name(), "greedy-picky");
+    }
+
+    #[test]
+    fn placed_body_links_up_from_head_to_tail() {
+        let mut field = Field::with_size(4, 4).unwrap();
+        let segments = [at(1, 1), at(2, 1), at(2, 2), at(1, 2), at(0, 2)];
+        field.place_body(&segments).unwrap();
+        assert_eq!(field.next(at(1, 1)), at(2, 1));
+        assert_eq!(field.next(at(2, 2)), at(1, 2));
+        assert_eq!(field.body(at(1, 1)), segments);
+        assert_eq!(field.drop_last_in_chain(at(1, 1)), at(0, 2));
+        assert_eq!(field.body(at(1, 1)), segments[..4]);
+        assert!(field.free_at(at(0, 2)));
+        assert!(field.place_body(&[at(0, 0), at(3, 3)]).is_err());
+    }
+
+    #[test]
+    fn impatient_snake_is_swapped_out_only_without_a_cycle() {
+        let name = |width, height| choose_snake(4, &Game::init(width, height)).name();
+        assert_eq!(name(7, 7), "hamiltonian");
+        assert_eq!(name(9, 7), "hamiltonian");
+        assert_eq!(name(8, 7), "impatient-hamiltonian");
+    }
+
+    #[test]
+    fn suspense_delay_slows_down_near_danger_within_its_bounds() {
+        let ms = time::Duration::from_millis;
+        let suspense = Suspense{range: 5, fastest: ms(50), slowest: ms(250)};
+        assert_eq!(suspense.delay(0), ms(250));
+        assert_eq!(suspense.delay(1), ms(250));
+        assert_eq!(suspense.delay(3), ms(150));
+        assert_eq!(suspense.delay(5), ms(50));
+        assert_eq!(suspense.delay(100), ms(50));
+        let delays: Vec<_> = (0..8).map(|distance| suspense.delay(distance)).collect();
+        assert!(delays.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn hamiltonian_boards_are_winnable_and_greedy_ones_mostly_not() {
+        for (width, height) in [(4, 4), (6, 4), (8, 8)] {
+            assert!(is_winnable(width, height, 7, &mut HamiltonianSnake::new()), "{}x{}", width, height);
+        }
+        let greedy_wins = (0..10).filter(|seed| is_winnable(12, 12, *seed, &mut GreedyPickySnake{})).count();
+        assert!(greedy_wins < 5);
+    }
+
+    #[test]
+    fn temporary_wall_blocks_then_opens_up() {
+        let mut game = Game::init_with_positions(5, 3, at(0, 1), at(4, 2)).unwrap();
+        assert!(game.add_temporary_wall(at(1, 1), 3));
+        assert_eq!(game.collision(Direction::Right), Some(StepOutcome::CrashedInWall));
+        game.step(Direction::Down);
+        game.step(Direction::Up);
+        assert_eq!(game.collision(Direction::Right), Some(StepOutcome::CrashedInWall));
+        game.step(Direction::Down);
+        game.step(Direction::Up);
+        assert_eq!(game.step(Direction::Right), StepOutcome::Moved);
+        assert_eq!(game.head(), at(1, 1));
+    }
+
+    #[test]
+    fn nearest_free_finds_the_closest_of_a_few_free_cells() {
+        let mut field = Field::with_size(7, 6).unwrap();
+        let free = [at(6, 0), at(2, 5), at(5, 4), at(0, 1)];
+        for y in 0..6 {
+            for x in 0..7 {
+                if !free.contains(&at(x, y)) {
+                    field.set_direction_at(at(x, y), Direction::Wall);
+                }
+            }
+        }
+        for from in [at(3, 2), at(0, 5), at(6, 5), at(1, 0)] {
+            let nearest = field.nearest_free(from).unwrap();
+            let closest = free.iter().map(|pos| pos.manhattan_distance(from)).min().unwrap();
+            assert_eq!(nearest.manhattan_distance(from), closest, "from {}", from);
+        }
+        assert_eq!(field.nearest_free(at(5, 4)), Some(at(5, 4)));
+    }
+
+    #[test]
+    fn cell_width_shrinks_to_fit_the_terminal() {
+        assert_eq!(fit_cell_width(3, 10, 80), Some(3));
+        assert_eq!(fit_cell_width(3, 30, 80), Some(2));
+        assert_eq!(fit_cell_width(3, 50, 80), Some(1));
+        assert_eq!(fit_cell_width(3, 100, 80), None);
+        assert_eq!(fit_cell_width(2, 10, 80), Some(2));
+    }
+
+    struct AppleCounter {
+        path: HamiltonianSnake,
+        eaten: u32,
+    }
+    impl Snake for AppleCounter {
+        fn name(&self) -> &'static str { "apple-counter" }
+        fn init(&mut self, game:&Game) {
+            self.path.init(game);
+        }
+        fn choose_direction(&mut self, game:&Game) -> Option<Direction> {
+            self.path.choose_direction(game)
+        }
+        fn on_apple_eaten(&mut self, _game:&Game) {
+            self.eaten += 1;
+        }
+    }
+
+    #[test]
+    fn on_apple_eaten_is_called_for_every_apple() {
+        let mut snake = AppleCounter{path: HamiltonianSnake::new(), eaten: 0};
+        let result = run(&mut Game::init_seeded(4, 4, 2), &mut snake, &RunOptions{max_moves: Some(60), ..RunOptions::default()});
+        assert!(result.apples > 2);
+        assert_eq!(snake.eaten, result.apples);
+    }
+
+    #[test]
+    fn seeded_game_reaches_a_pinned_state_hash() {
+        let mut game = Game::init_seeded(6, 6, 1234);
+        run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(40), ..RunOptions::default()});
+        assert_eq!(game.moves(), 40);
+        /* changes whenever the rules or the seeded randomness do */
+        assert_eq!(game.state_hash(), 0x9e7fb3435b647d48);
+    }
+
+    #[test]
+    fn body_arrows_are_drawn_only_when_enabled() {
+        let mut game = Game::init_with_positions(5, 3, at(2, 1), at(4, 2)).unwrap();
+        game.force_body(&[at(2, 1), at(1, 1), at(0, 1)]);
+        let arrows = |frame:&str| ["🡸", "🡺", "🡹", "🡻"].iter().any(|arrow| frame.contains(arrow));
+        game.set_theme(Theme{show_body_arrows: true, ..Theme::default()});
+        assert!(arrows(&game.render()));
+        game.set_theme(Theme{show_body_arrows: false, ..Theme::default()});
+        let frame = game.render();
+        assert!(!arrows(&frame));
+        assert_eq!(frame.matches('■').count(), 2);
+    }
+
+    #[test]
+    fn apple_placement_strategies_pick_the_cells_they_promise() {
+        let mut game = Game::init_with_positions(5, 4, at(1, 1), at(4, 3)).unwrap();
+        let head = game.head();
+        let farthest = game.field().free_cells().map(|pos| pos.manhattan_distance(head)).max().unwrap();
+
+        assert!(game.set_apple_placement(ApplePlacement::FarFromHead));
+        assert_eq!(game.apple().manhattan_distance(head), farthest);
+        assert!(game.set_apple_placement(ApplePlacement::NearHead));
+        assert_eq!(game.apple().manhattan_distance(head), 1);
+        assert!(game.set_apple_placement(ApplePlacement::Fixed(at(3, 0))));
+        assert_eq!(game.apple(), at(3, 0));
+        /* a fixed spot under the head falls back to any free cell */
+        assert!(game.set_apple_placement(ApplePlacement::Fixed(head)));
+        assert!(game.field().free_at(game.apple()) && game.apple() != head);
+        for _ in 0..10 {
+            assert!(game.set_apple_placement(ApplePlacement::Uniform));
+            assert!(game.field().free_at(game.apple()) && game.apple() != head);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Occupied cells don't match the snake length")]
+    fn invariant_check_catches_a_corrupted_field() {
+        let mut game = Game::init_with_positions(4, 4, at(1, 1), at(3, 3)).unwrap();
+        game.set_debug_invariants(true);
+        game.step(Direction::Right);
+        game.field.set_direction_at(at(0, 3), Direction::Left);
+        game.step(Direction::Down);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected exactly one tail end")]
+    fn invariant_check_catches_a_second_tail() {
+        let mut game = Game::init_with_positions(4, 4, at(1, 1), at(3, 3)).unwrap();
+        game.field.set_direction_at(at(0, 3), Direction::End);
+        game.check_invariants();
+    }
+
+    #[test]
+    fn replay_round_trips_through_text() {
+        let mut game = Game::init_seeded(6, 5, 77);
+        run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(150), ..RunOptions::default()});
+        let replay = game.record("hamiltonian").unwrap();
+        assert!(replay.directions.len() > Replay::LINE_LENGTH);
+        let text = replay.to_text();
+        assert!(text.starts_with("seed=77 width=6 height=5 snake=hamiltonian\n"));
+        assert_eq!(Replay::from_text(&text), Ok(replay));
+        assert!(Replay::from_text("seed=x\n").is_err());
+
+        let spaced = game.record("my snake  v2 ").unwrap();
+        assert_eq!(Replay::from_text(&spaced.to_text()), Ok(spaced));
+        assert!(game.record("two\nlines").is_err());
+    }
+
+    #[test]
+    fn weighted_a_star_finds_a_valid_path_expanding_fewer_cells() {
+        let mut game = Game::init_with_positions(60, 60, at(2, 30), at(57, 30)).unwrap();
+        for y in 5..55 {
+            game.field.set_direction_at(at(30, y), Direction::Wall);
+        }
+        let search = |weight:f32| {
+            let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+            let counter = calls.clone();
+            let snake = AStarSnake::with_heuristic(Box::new(move |a, b| {
+                counter.set(counter.get() + 1);
+                weight * a.manhattan_distance(b) as f32
+            }));
+            let path = snake.path(&game, game.head(), game.apple()).unwrap();
+            (path, calls.get())
+        };
+        let (plain, plain_calls) = search(1.0);
+        let (weighted, weighted_calls) = search(3.0);
+        assert!(weighted_calls < plain_calls);
+        assert!(weighted.len() >= plain.len());
+        assert_eq!(weighted.last(), Some(&game.apple()));
+        let steps = std::iter::once(game.head()).chain(weighted.iter().copied()).collect::<Vec<_>>();
+        assert!(steps.windows(2).all(|pair| pair[0].manhattan_distance(pair[1]) == 1 && game.field().free_at(pair[1])));
+    }
+
+    #[test]
+    fn is_border_tells_corners_and_edges_from_the_inside() {
+        let field = Field::with_size(4, 4).unwrap();
+        for corner in [at(0, 0), at(3, 0), at(0, 3), at(3, 3)] {
+            assert!(field.is_border(corner));
+        }
+        for edge in [at(1, 0), at(0, 2), at(3, 1), at(2, 3)] {
+            assert!(field.is_border(edge));
+        }
+        for inside in [at(1, 1), at(2, 1), at(1, 2), at(2, 2)] {
+            assert!(!field.is_border(inside));
+        }
+    }
+
+    #[test]
+    fn greedy_preferences_are_always_a_permutation() {
+        let head = at(10, 10);
+        for dx in -10..=10 {
+            for dy in -10..=10 {
+                let mut preferred = GreedyPickySnake::prioritize(head, at(10 + dx, 10 + dy)).to_vec();
+                preferred.sort_by_key(|dir| *dir as u8);
+                let mut all = Direction::MOVES.to_vec();
+                all.sort_by_key(|dir| *dir as u8);
+                assert_eq!(preferred, all, "delta ({}, {})", dx, dy);
+            }
+        }
+    }
+
+    #[test]
+    fn run_until_apples_stops_right_at_the_target() {
+        let mut game = Game::init_seeded(6, 6, 8);
+        let result = run_until_apples(&mut game, &mut HamiltonianSnake::new(), 5, &RunOptions::default());
+        assert_eq!(result.end, GameEnd::ReachedAppleTarget);
+        assert_eq!((result.apples, game.length()), (5, 6));
+    }
+
+    #[test]
+    fn apple_placed_on_the_body_moves_to_the_one_free_cell() {
+        let mut game = Game::init_with_positions(3, 2, at(0, 0), at(2, 1)).unwrap();
+        game.force_body(&[at(0, 0), at(1, 0), at(2, 0), at(2, 1), at(1, 1)]);
+        assert!(game.set_apple_placement(ApplePlacement::Fixed(at(2, 0))));
+        assert_eq!(game.apple(), at(0, 1));
+        assert!(game.set_apple_placement(ApplePlacement::Fixed(at(0, 0))));
+        assert_eq!(game.apple(), at(0, 1));
+        game.field.set_direction_at(at(0, 1), Direction::Wall);
+        assert!(!game.set_apple_placement(ApplePlacement::NearHead));
+    }
+
+    #[test]
+    fn completion_ratio_reaches_one_on_a_won_game() {
+        let mut game = Game::init_seeded(4, 4, 6);
+        assert_eq!(game.completion_ratio(), 0.0);
+        let result = run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(move_budget(4, 4)), ..RunOptions::default()});
+        assert!(result.won());
+        assert!((game.completion_ratio() - 1.0).abs() < 1e-6);
+        assert_eq!(max_theoretical_apples(4, 4), 15);
+    }
+
+    #[test]
+    fn opportunistic_snake_grabs_an_adjacent_apple_and_rejoins_the_cycle() {
+        let mut game = Game::init_with_positions(6, 6, at(0, 2), at(1, 2)).unwrap();
+        let mut snake = OpportunisticHamiltonianSnake::new();
+        snake.init(&game);
+        /* the cycle goes on down to (0, 3) */
+        assert_eq!(snake.choose_direction(&game), Some(Direction::Right));
+        assert_eq!(game.step(Direction::Right), StepOutcome::AteApple);
+        /* and from (1, 2) up to (1, 1) */
+        assert_eq!(snake.choose_direction(&game), Some(Direction::Up));
+        let result = run(&mut game, &mut snake, &RunOptions{max_moves: Some(move_budget(6, 6)), ..RunOptions::default()});
+        assert!(result.won());
+    }
+
+    #[test]
+    fn is_legal_move_agrees_with_step() {
+        let mut game = Game::init_with_positions(4, 3, at(1, 1), at(3, 2)).unwrap();
+        game.force_body(&[at(1, 0), at(2, 0), at(2, 1), at(1, 1)]);
+        game.field.set_direction_at(at(0, 0), Direction::Wall);
+        let mut legal = Vec::new();
+        for dir in [Direction::Left, Direction::Right, Direction::Up, Direction::Down, Direction::End, Direction::Wall] {
+            let committed = game.clone().step(dir).committed();
+            assert_eq!(game.is_legal_move(dir), committed, "{:?}", dir);
+            if committed {
+                legal.push(dir);
+            }
+        }
+        /* not the wall, the neck or over the top edge, but the tail is fine */
+        assert_eq!(legal, vec![Direction::Down]);
+    }
+
+    #[test]
+    fn horizontal_wrap_wraps_sideways_but_crashes_top_and_bottom() {
+        let mut game = Game::init_with_positions(4, 3, at(0, 1), at(2, 2)).unwrap();
+        game.set_edges(EdgeModes{left: EdgeMode::Wrap, right: EdgeMode::Wrap, ..EdgeModes::default()});
+        assert_eq!(game.step(Direction::Left), StepOutcome::Moved);
+        assert_eq!(game.head(), at(3, 1));
+        assert_eq!(game.step(Direction::Right), StepOutcome::Moved);
+        assert_eq!(game.head(), at(0, 1));
+        game.step(Direction::Up);
+        assert_eq!(game.step(Direction::Up), StepOutcome::CrashedInWall);
+        let mut game = Game::init_with_positions(4, 3, at(1, 2), at(2, 0)).unwrap();
+        game.set_edges(EdgeModes{left: EdgeMode::Wrap, right: EdgeMode::Wrap, ..EdgeModes::default()});
+        assert_eq!(game.step(Direction::Down), StepOutcome::CrashedInWall);
+    }
+
+    #[test]
+    fn accessors_report_the_state_after_a_move() {
+        let mut game = Game::init_with_positions(5, 4, at(1, 1), at(2, 1)).unwrap();
+        assert_eq!(game.step(Direction::Right), StepOutcome::AteApple);
+        assert_eq!(game.head(), at(2, 1));
+        assert_ne!(game.apple(), at(2, 1));
+        assert!(game.field().free_at(game.apple()));
+        assert_eq!(game.field().dimension(), at(5, 4));
+        assert_eq!((game.apples(), game.moves(), game.length()), (1, 1, 2));
+        assert_eq!(game.seed(), 42);
+        assert_eq!(game.mode(), GameMode::Classic);
+        assert_eq!(*game.theme(), Theme::default());
+        assert_eq!(game.body(), vec![at(2, 1), at(1, 1)]);
+    }
+
+    #[test]
+    fn iter_chain_yields_the_body_in_order() {
+        let mut field = Field::with_size(3, 3).unwrap();
+        let segments = [at(0, 0), at(1, 0), at(2, 0), at(2, 1), at(1, 1), at(0, 1), at(0, 2)];
+        field.place_body(&segments).unwrap();
+        assert_eq!(field.iter_chain(at(0, 0)).collect::<Vec<_>>(), segments);
+        assert_eq!(field.iter_chain(at(2, 1)).collect::<Vec<_>>(), segments[3..]);
+        assert_eq!(field.find_last(at(0, 0)), (at(0, 2), at(0, 1)));
+    }
+
+    #[test]
+    fn shuffle_bag_hands_out_every_direction_once_per_four_calls() {
+        let game = Game::init(5, 5);
+        let mut snake = ShuffleBagSnake::new();
+        snake.init(&game);
+        for _ in 0..25 {
+            let mut bag: Vec<Direction> = (0..4).map(|_| snake.choose_direction(&game).unwrap()).collect();
+            bag.sort_by_key(|dir| *dir as u8);
+            assert_eq!(bag, Direction::MOVES);
+        }
+    }
+
+    #[test]
+    fn apple_despawns_after_its_lifetime() {
+        let mut game = Game::init_with_positions(5, 5, at(0, 0), at(4, 4)).unwrap();
+        game.set_apple_lifetime(Some(4), 2);
+        let idle = [Direction::Right, Direction::Left, Direction::Right];
+        let outcomes = force_transitions(&mut game, &idle);
+        assert_eq!(outcomes, vec![StepOutcome::Moved; 3]);
+        assert_eq!((game.apple(), game.missed_apples()), (at(4, 4), 0));
+        game.step(Direction::Left);
+        assert_ne!(game.apple(), at(4, 4));
+        assert!(game.field().free_at(game.apple()));
+        assert_eq!(game.missed_apples(), 1);
+        assert_eq!(game.score(), -2);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn png_export_is_cell_size_times_the_board() {
+        let game = Game::init_seeded(7, 5, 3);
+        let path = std::env::temp_dir().join(format!("snake-board-{}.png", std::process::id()));
+        game.write_png(&path, 4).unwrap();
+        let image = image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (28, 20));
+    }
+
+    #[test]
+    fn connectivity_snake_does_not_cut_the_board_in_two() {
+        /* going down for the apple walls off the left from the right */
+        let mut game = Game::init_with_positions(5, 3, at(2, 1), at(2, 2)).unwrap();
+        game.force_body(&[at(2, 1), at(2, 0)]);
+        assert!(game.is_legal_move(Direction::Down));
+        let mut after = game.clone();
+        after.step(Direction::Down);
+        assert_eq!(after.field().free_regions(), 2);
+        let dir = ConnectivitySnake{}.choose_direction(&game).unwrap();
+        assert_ne!(dir, Direction::Down);
+        game.step(dir);
+        assert_eq!(game.field().free_regions(), 1);
+    }
+
+    #[test]
+    fn training_data_has_a_feature_row_and_label_per_move() {
+        let moves = [Direction::Right, Direction::Down, Direction::Right];
+        let mut snake = TrainingDataSnake::new(ScriptedSnake::new(&moves), Vec::new());
+        let mut game = Game::init_with_positions(5, 4, at(0, 0), at(4, 3)).unwrap();
+        run(&mut game, &mut snake, &RunOptions::default());
+        let csv = String::from_utf8(snake.finish().unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + moves.len());
+        let columns = lines[0].split(',').count();
+        assert_eq!(columns, 10);
+        for (line, dir) in lines[1..].iter().zip(moves) {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(), columns);
+            assert_eq!(fields[columns-1], dir.to_char().to_string());
+        }
+        /* in the top left corner left and up crash, and every other cell is in reach */
+        assert_eq!(lines[1], "0,0,4,3,1,0,1,0,19,R");
+    }
+
+    #[test]
+    fn distance_map_is_manhattan_on_an_open_board_and_none_where_taken() {
+        let mut field = Field::with_size(5, 4).unwrap();
+        let from = at(1, 2);
+        let distances = field.distance_map(from);
+        for y in 0..4 {
+            for x in 0..5 {
+                assert_eq!(distances[y as usize][x as usize], Some(at(x, y).manhattan_distance(from) as usize));
+            }
+        }
+        field.place_body(&[at(3, 0), at(3, 1), at(3, 2)]).unwrap();
+        let distances = field.distance_map(from);
+        for taken in [at(3, 0), at(3, 1), at(3, 2)] {
+            assert_eq!(distances[taken.y as usize][taken.x as usize], None);
+        }
+        assert_eq!(distances[0][4], Some(7));
+    }
+
+    #[test]
+    fn teleport_leaves_the_body_behind_as_walls() {
+        let mut game = Game::init_with_positions(5, 5, at(0, 0), at(1, 0)).unwrap();
+        game.set_mode(GameMode::Teleport);
+        assert_eq!(game.step(Direction::Right), StepOutcome::AteApple);
+        for old in [at(0, 0), at(1, 0)] {
+            assert_ne!(game.head(), old);
+            assert_eq!(game.field().get_direction_at(old), Direction::Wall);
+        }
+        assert_eq!(game.length(), 1);
+        assert_eq!(game.body(), vec![game.head()]);
+        game.check_invariants();
+    }
+
+    #[test]
+    fn clamp_pulls_coordinates_onto_the_board() {
+        let board = at(5, 3);
+        assert_eq!(at(2, 1).clamp(board), Some(at(2, 1)));
+        assert_eq!(at(-3, 1).clamp(board), Some(at(0, 1)));
+        assert_eq!(at(9, -1).clamp(board), Some(at(4, 0)));
+        assert_eq!(at(5, 3).clamp(board), Some(at(4, 2)));
+        assert_eq!(at(-1, 7).clamp(board), Some(at(0, 2)));
+    }
+
+    #[test]
+    fn playback_speed_and_pause_follow_the_keys() {
+        let ms = time::Duration::from_millis;
+        let mut playback = Playback::new(ms(80));
+        let mut states = Vec::new();
+        for key in ['+', '+', '+', '+', 'p', 'x', '-', 'p', '-', '-', '-', '-', '-', '-', '-', '-'] {
+            playback.key(key);
+            states.push((playback.delay(), playback.paused()));
+        }
+        assert_eq!(states[..4], [(ms(40), false), (ms(20), false), (ms(10), false), (ms(10), false)]);
+        assert_eq!(states[4..8], [(ms(10), true), (ms(10), true), (ms(20), true), (ms(20), false)]);
+        assert_eq!(states.last(), Some(&(time::Duration::from_secs(2), false)));
+
+        let mut clock = MockClock::new();
+        playback.wait(&mut clock);
+        assert_eq!(clock.now(), time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn direction_predicates_over_every_variant() {
+        use Direction::*;
+        let all = [Left, Right, Up, Down, End, Null, Wall];
+        let truth = |predicate:fn(&Direction) -> bool| all.iter().map(predicate).collect::<Vec<_>>();
+        assert_eq!(truth(Direction::is_movement), [true, true, true, true, false, false, false]);
+        assert_eq!(truth(Direction::is_tail),     [false, false, false, false, true, false, false]);
+        assert_eq!(truth(Direction::is_empty),    [false, false, false, false, false, true, false]);
+        assert_eq!(truth(Direction::is_snake),    [true, true, true, true, true, false, false]);
+        for dir in all {
+            assert_eq!(Direction::from_char(dir.to_char()), Some(dir));
+        }
+    }
+
+    #[test]
+    fn sweep_times_every_board_size() {
+        let timings = sweep(&mut HamiltonianSnake::new(), &[(4, 4), (6, 4)], 50);
+        assert_eq!(timings.len(), 2);
+        assert_eq!((timings[0].width, timings[0].height), (4, 4));
+        assert_eq!((timings[1].width, timings[1].height), (6, 4));
+        assert!(timings.iter().all(|timing| timing.moves > 0 && timing.moves <= 50));
+    }
+
+    #[test]
+    fn game_starts_from_the_picked_head_and_apple() {
+        let mut game = Game::init_with_positions(6, 4, at(4, 2), at(1, 1)).unwrap();
+        assert_eq!((game.head(), game.apple()), (at(4, 2), at(1, 1)));
+        assert_eq!(game.step(Direction::Up), StepOutcome::Moved);
+        assert_eq!(game.step(Direction::Left), StepOutcome::Moved);
+        assert_eq!((game.head(), game.length()), (at(3, 1), 1));
+        assert!(Game::init_with_positions(6, 4, at(6, 0), at(1, 1)).is_err());
+        assert!(Game::init_with_positions(6, 4, at(1, 1), at(1, 1)).is_err());
+    }
+
+    #[test]
+    fn length_goal_wins_with_room_to_spare() {
+        let mut game = Game::init_seeded(6, 6, 11);
+        game.set_length_goal(Some(5));
+        let result = run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(move_budget(6, 6)), ..RunOptions::default()});
+        assert_eq!(result.end, GameEnd::Finished(StepOutcome::Won));
+        assert_eq!(game.length(), 5);
+        assert!(game.free_cells() > 0);
+    }
+
+    #[test]
+    fn direction_counts_tally_committed_moves() {
+        let mut game = Game::init_with_positions(5, 5, at(2, 2), at(0, 0)).unwrap();
+        let moves = [Direction::Right, Direction::Down, Direction::Left, Direction::Left, Direction::Up, Direction::Wall];
+        force_transitions(&mut game, &moves);
+        /* in Direction::MOVES order: left, right, up, down */
+        assert_eq!(game.direction_counts(), [2, 1, 1, 1]);
+    }
+
+    #[test]
+    fn greedy_picky_finds_the_one_way_out() {
+        let mut game = Game::init_with_positions(4, 3, at(1, 1), at(0, 0)).unwrap();
+        game.force_body(&[at(1, 1), at(1, 0), at(2, 0)]);
+        game.field.set_direction_at(at(0, 1), Direction::Wall);
+        game.field.set_direction_at(at(1, 2), Direction::Wall);
+        assert_eq!(GreedyPickySnake{}.choose_direction(&game), Some(Direction::Right));
+
+        /* the only way out is over a wrapping edge */
+        let mut game = Game::init_with_positions(4, 3, at(0, 1), at(2, 0)).unwrap();
+        game.set_edges(EdgeModes{left: EdgeMode::Wrap, ..EdgeModes::default()});
+        game.force_body(&[at(0, 1), at(0, 0), at(1, 0)]);
+        game.field.set_direction_at(at(1, 1), Direction::Wall);
+        game.field.set_direction_at(at(0, 2), Direction::Wall);
+        assert_eq!(GreedyPickySnake{}.choose_direction(&game), Some(Direction::Left));
+        assert_eq!(game.step(Direction::Left), StepOutcome::Moved);
+        assert_eq!(game.head(), at(3, 1));
+    }
+
+    #[test]
+    fn cycle_overlay_points_along_the_cycle() {
+        let mut game = Game::init_with_positions(4, 4, at(0, 0), at(3, 3)).unwrap();
+        assert_eq!(game.cycle_overlay_at(at(1, 1)), None);
+        let cycle = hamiltonian_cycle(4, 4).unwrap();
+        game.set_cycle_overlay(Some(&cycle));
+        for (i, pos) in cycle.iter().enumerate() {
+            let next = cycle[(i+1) % cycle.len()];
+            assert_eq!(game.cycle_overlay_at(*pos), pos.direction_to(next));
+        }
+        game.set_cycle_overlay(None);
+        assert_eq!(game.cycle_overlay_at(at(1, 1)), None);
+    }
+
+    #[test]
+    fn assist_overrides_a_suicidal_key() {
+        let mut game = Game::init_with_positions(5, 5, at(2, 2), at(0, 4)).unwrap();
+        game.force_body(&[at(2, 2), at(1, 2), at(0, 2)]);
+        let (keys, received) = std::sync::mpsc::channel();
+        let mut snake = HumanSnake::with_keys(received, Assist::KeepHeading);
+        snake.init(&game);
+        /* left would turn back into the neck */
+        keys.send('a').unwrap();
+        assert_eq!(snake.choose_direction(&game), Some(Direction::Right));
+
+        /* with the heading blocked as well, only a safe move will do */
+        game.field.set_direction_at(at(3, 2), Direction::Wall);
+        let (keys, received) = std::sync::mpsc::channel();
+        let mut snake = HumanSnake::with_keys(received, Assist::AnySafeMove);
+        snake.init(&game);
+        keys.send('a').unwrap();
+        let chosen = snake.choose_direction(&game).unwrap();
+        assert!(game.safe_moves().contains(&chosen));
+        assert!(game.step(chosen).committed());
+
+        let (keys, received) = std::sync::mpsc::channel();
+        let mut snake = HumanSnake::with_keys(received, Assist::Off);
+        snake.init(&game);
+        keys.send('s').unwrap();
+        keys.send('a').unwrap();
+        assert_eq!(snake.choose_direction(&game), Some(Direction::Left));
+    }
+
+    #[test]
+    fn body_bounds_enclose_an_l_shaped_body() {
+        let mut game = Game::init_with_positions(6, 6, at(3, 1), at(0, 5)).unwrap();
+        assert_eq!(game.body_bounds(), (at(3, 1), at(3, 1)));
+        game.force_body(&[at(1, 4), at(2, 4), at(3, 4), at(3, 3), at(3, 2)]);
+        assert_eq!(game.body_bounds(), (at(1, 2), at(3, 4)));
+    }
+
+    #[test]
+    fn failing_seed_is_found_for_a_weak_snake_only() {
+        let seed = find_failing_seed(&mut SillySnake::new(), 6, 6, 0..10).expect("the silly snake dies on some seed");
+        let options = RunOptions{max_moves: Some(move_budget(6, 6)), ..RunOptions::default()};
+        for earlier in 0..seed {
+            assert!(!run(&mut Game::init_seeded(6, 6, earlier), &mut SillySnake::new(), &options).died());
+        }
+        assert!(run(&mut Game::init_seeded(6, 6, seed), &mut SillySnake::new(), &options).died());
+        assert_eq!(find_failing_seed(&mut HamiltonianSnake::new(), 6, 6, 0..10), None);
+    }
+
+    #[test]
+    fn draw_to_writes_the_same_bytes_as_render() {
+        let mut game = Game::init_seeded(7, 5, 3);
+        run(&mut game, &mut GreedySnake{}, &RunOptions{max_moves: Some(12), ..RunOptions::default()});
+        let mut out = Vec::new();
+        game.draw_to(&mut out).unwrap();
+        assert_eq!(out, game.render().into_bytes());
+    }
+
+    #[test]
+    fn first_move_follows_the_initial_heading() {
+        let mut game = Game::init_with_positions(5, 5, at(0, 2), at(4, 4)).unwrap();
+        assert!(game.set_initial_heading(Direction::Left).is_err());
+        assert!(game.set_initial_heading(Direction::End).is_err());
+        game.set_initial_heading(Direction::Down).unwrap();
+        assert_eq!(game.last_direction(), Some(Direction::Down));
+        let (_keys, received) = std::sync::mpsc::channel();
+        let mut snake = HumanSnake::with_keys(received, Assist::Off);
+        snake.init(&game);
+        let dir = snake.choose_direction(&game).unwrap();
+        assert_eq!(dir, Direction::Down);
+        assert_eq!(game.step(dir), StepOutcome::Moved);
+        assert_eq!(game.head(), at(0, 3));
+        assert!(game.set_initial_heading(Direction::Right).is_err());
+    }
+
+    #[test]
+    fn level_apples_appear_one_after_the_other() {
+        let level = Level::from_text("#####\n#@#2#\n#1..#\n#####\n").unwrap();
+        let mut game = Game::from_level(&level).unwrap();
+        assert_eq!(game.apple(), at(1, 2));
+        assert_eq!(game.apples_left_in_sequence(), 1);
+        assert_eq!(game.step(Direction::Down), StepOutcome::AteApple);
+        assert_eq!(game.apple(), at(3, 1));
+        assert_eq!(game.apples_left_in_sequence(), 0);
+        assert_eq!(game.step(Direction::Right), StepOutcome::Moved);
+        assert_eq!(game.step(Direction::Right), StepOutcome::Moved);
+        assert_eq!(game.step(Direction::Up), StepOutcome::Won);
+    }
+
+    #[test]
+    fn sealed_pocket_is_an_enclosed_region() {
+        let mut game = Game::init_with_positions(5, 5, at(0, 0), at(4, 4)).unwrap();
+        assert!(!game.is_in_enclosed_region());
+        for pos in [at(2, 0), at(2, 1), at(2, 2), at(1, 2), at(0, 2)] {
+            game.field.set_direction_at(pos, Direction::Wall);
+        }
+        assert!(game.is_in_enclosed_region());
+        game.field.set_direction_at(at(2, 1), Direction::Null);
+        assert!(!game.is_in_enclosed_region());
+    }
+
+    #[test]
+    fn death_reasons_format_to_their_messages() {
+        assert_eq!(DeathReason::Forfeit.to_string(), "Snake forfeit.");
+        assert_eq!(DeathReason::Gibberish.to_string(), "Snake is ejected because it speaks gibberish.");
+        assert_eq!(DeathReason::CrashedInWall.to_string(), "crashed in wall.");
+        assert_eq!(DeathReason::AteSnake.to_string(), "ate snake");
+    }
+
+    #[test]
+    fn coord_of_undoes_index_of_for_every_cell() {
+        let field = Field::with_size(4, 3).unwrap();
+        for y in 0..3 {
+            for x in 0..4 {
+                let index = field.index_of(at(x, y));
+                assert_eq!(index, (y*4 + x) as usize);
+                assert_eq!(field.coord_of(index), at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn center_seeking_snake_drifts_to_the_middle_when_it_cannot_close_in() {
+        let mut game = Game::init_with_positions(7, 7, at(1, 2), at(0, 6)).unwrap();
+        assert_eq!(CenterSeekingSnake{}.choose_direction(&game), Some(Direction::Left));
+        game.field.set_direction_at(at(0, 2), Direction::Wall);
+        game.field.set_direction_at(at(1, 3), Direction::Wall);
+        assert_eq!(CenterSeekingSnake{}.choose_direction(&game), Some(Direction::Right));
+    }
+
+    #[test]
+    fn breakpoints_report_what_triggered_them() {
+        let mut game = Game::init_with_positions(5, 5, at(0, 0), at(1, 0)).unwrap();
+        let reachable = game.field.reachable_count(game.head());
+        let outcome = game.step(Direction::Right);
+        assert_eq!(outcome, StepOutcome::AteApple);
+        assert_eq!(Breakpoints::default().check(&game, outcome, reachable), None);
+        let apple = Breakpoints{apple_eaten: true, ..Breakpoints::default()};
+        assert_eq!(apple.check(&game, outcome, reachable), Some(Breakpoint::AppleEaten));
+        let length = Breakpoints{length: Some(2), ..Breakpoints::default()};
+        assert_eq!(length.check(&game, outcome, reachable), Some(Breakpoint::Length(2)));
+        assert_eq!(length.check(&game, StepOutcome::Moved, reachable), None);
+
+        let cramped = Breakpoints{reachable_below: Some(3), ..Breakpoints::default()};
+        let mut game = Game::init_with_positions(3, 3, at(1, 1), at(2, 2)).unwrap();
+        assert_eq!(cramped.check(&game, StepOutcome::Moved, 8), None);
+        for pos in [at(0, 1), at(1, 0), at(2, 0), at(2, 1), at(1, 2), at(2, 2)] {
+            game.field.set_direction_at(pos, Direction::Wall);
+        }
+        assert_eq!(cramped.check(&game, StepOutcome::Moved, 8), Some(Breakpoint::ReachableBelow(3)));
+        assert_eq!(cramped.check(&game, StepOutcome::Moved, 2), None);
+    }
+
+    #[test]
+    fn fingerprint_is_stable_until_the_state_changes() {
+        let game = Game::init_seeded(5, 5, 42);
+        let fingerprint = game.fingerprint();
+        assert!(fingerprint.starts_with("5x5-s42-m0-"));
+        assert_eq!(Game::init_seeded(5, 5, 42).fingerprint(), fingerprint);
+        assert_ne!(Game::init_seeded(5, 5, 43).fingerprint(), fingerprint);
+        let mut moved = Game::init_seeded(5, 5, 42);
+        let dir = moved.safe_moves()[0];
+        moved.step(dir);
+        assert!(moved.fingerprint().contains("-m1-"));
+        assert_ne!(moved.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn game_goes_on_after_a_win_when_asked() {
+        let budget = move_budget(4, 4);
+        let options = RunOptions{max_moves: Some(budget), continue_after_win: true, ..RunOptions::default()};
+        let mut game = Game::init_seeded(4, 4, 5);
+        let result = run(&mut game, &mut HamiltonianSnake::new(), &options);
+        assert!(result.won());
+        assert_eq!(result.end, GameEnd::OutOfMoves);
+        assert_eq!(result.moves, budget);
+        let won_at = result.won_at.unwrap();
+        assert!(won_at < budget);
+        assert_eq!(result.survived_after_win(), Some(budget - won_at));
+
+        let options = RunOptions{continue_after_win: false, ..options};
+        let result = run(&mut Game::init_seeded(4, 4, 5), &mut HamiltonianSnake::new(), &options);
+        assert_eq!((result.end, result.moves, result.won_at), (GameEnd::Finished(StepOutcome::Won), won_at, None));
+    }
+
+    #[test]
+    fn field_with_size_refuses_an_empty_board() {
+        assert!(Field::with_size(0, 5).is_err());
+        assert!(Field::with_size(5, 0).is_err());
+        let field = Field::with_size(5, 3).unwrap();
+        assert_eq!(field.dimension, at(5, 3));
+        assert_eq!(field.free_cells().count(), 15);
+    }
+
+    #[test]
+    fn queued_keys_are_taken_one_per_move_without_reversals() {
+        let mut game = Game::init_with_positions(7, 7, at(2, 2), at(0, 6)).unwrap();
+        let (keys, received) = std::sync::mpsc::channel();
+        let mut snake = HumanSnake::with_keys(received, Assist::Off);
+        snake.set_queued_input(true);
+        snake.init(&game);
+        for key in "wsds".chars() {
+            keys.send(key).unwrap();
+        }
+        let mut made = Vec::new();
+        for _ in 0..4 {
+            let dir = snake.choose_direction(&game).unwrap();
+            assert_eq!(game.step(dir), StepOutcome::Moved);
+            made.push(dir);
+        }
+        /* the s right after w would turn back and is dropped */
+        assert_eq!(made, [Direction::Up, Direction::Right, Direction::Down, Direction::Down]);
+        assert_eq!(game.head(), at(3, 3));
+    }
+
+    #[test]
+    fn progress_bar_fills_with_the_completion_ratio() {
+        let mut game = Game::init_with_positions(3, 3, at(0, 0), at(2, 2)).unwrap();
+        assert_eq!(game.progress_bar(4), "[░░░░]");
+        game.force_body(&[at(0, 2), at(0, 1), at(0, 0), at(1, 0), at(2, 0)]);
+        assert_eq!(game.completion_ratio(), 0.5);
+        assert_eq!(game.progress_bar(10), "[█████░░░░░]");
+        assert_eq!(game.progress_bar(3), "[█░░]");
+    }
+
+    #[test]
+    fn apple_pattern_alternates_between_its_cells() {
+        let mut game = Game::init_with_positions(5, 5, at(2, 2), at(0, 0)).unwrap();
+        assert!(game.set_apple_pattern(&[at(3, 2), at(1, 2)]));
+        assert_eq!(game.apple(), at(3, 2));
+        assert_eq!(game.step(Direction::Right), StepOutcome::AteApple);
+        assert_eq!(game.apple(), at(1, 2));
+        let moves = [Direction::Down, Direction::Left, Direction::Left, Direction::Up];
+        assert_eq!(force_transitions(&mut game, &moves).last(), Some(&StepOutcome::AteApple));
+        assert_eq!(game.apple(), at(3, 2));
+    }
+
+    #[test]
+    fn only_the_random_and_human_snakes_are_nondeterministic() {
+        let game = Game::init_seeded(6, 6, 1);
+        for (k, name) in BUILTIN_SNAKES.iter().enumerate() {
+            if *name == "human" {
+                continue; /* would read stdin */
+            }
+            assert_eq!(choose_snake(k as u32, &game).is_deterministic(), *name != "shuffle-bag", "{}", name);
+        }
+        let (_keys, received) = std::sync::mpsc::channel();
+        assert!(!HumanSnake::with_keys(received, Assist::Off).is_deterministic());
+        /* seeded from the game, see SillySnake::init() */
+        assert!(SillySnake::new().is_deterministic());
+    }
+
+    #[test]
+    fn silly_snake_repeats_itself_on_the_same_seed() {
+        let play = |seed:u64| {
+            let mut game = Game::init_seeded(8, 8, seed);
+            let mut snake = SillySnake::new();
+            run(&mut game, &mut snake, &RunOptions{max_moves: Some(200), ..RunOptions::default()});
+            game.record(snake.name()).unwrap().directions
+        };
+        let moves = play(7);
+        assert!(!moves.is_empty());
+        assert_eq!(play(7), moves);
+    }
+
+    #[test]
+    fn cycle_snakes_refuse_boards_with_walls() {
+        let plain = GameConfig::default();
+        let walled = GameConfig{wall_density: 0.1, ..GameConfig::default()};
+        let teleport = GameConfig{mode: GameMode::Teleport, ..GameConfig::default()};
+        for snake in [&HamiltonianSnake::new() as &dyn Snake, &ImpatientHamiltonianSnake::new(), &OpportunisticHamiltonianSnake::new()] {
+            assert_eq!(snake.supports(&plain), Ok(()));
+            assert!(snake.supports(&walled).is_err());
+            assert!(snake.supports(&teleport).is_err());
+        }
+        assert_eq!(GreedySnake{}.supports(&walled), Ok(()));
+    }
+
+    #[test]
+    fn diff_of_a_step_is_the_new_head_and_both_tail_cells() {
+        let mut game = Game::init_with_positions(5, 5, at(2, 2), at(4, 4)).unwrap();
+        game.force_body(&[at(2, 2), at(1, 2), at(0, 2)]);
+        let before = game.clone();
+        assert_eq!(game.step(Direction::Right), StepOutcome::Moved);
+        assert_eq!(before.diff(&game), [
+            (at(0, 2), Direction::End, Direction::Null),
+            (at(1, 2), Direction::Left, Direction::End),
+            (at(3, 2), Direction::Null, Direction::Left),
+        ]);
+        assert!(game.diff(&game).is_empty());
+    }
+
+    #[test]
+    fn registered_snake_is_made_by_name() {
+        struct StraightSnake;
+        impl Snake for StraightSnake {
+            fn name(&self) -> &'static str { "straight" }
+            fn init(&mut self, _game:&Game) { }
+            fn choose_direction(&mut self, _game:&Game) -> Option<Direction> { Some(Direction::Right) }
+        }
+        let game = Game::init_seeded(5, 5, 1);
+        assert!(snake_by_name("straight", &game).is_none());
+        register_snake("straight", || Box::new(StraightSnake)).unwrap();
+        assert_eq!(snake_by_name("straight", &game).map(|snake| snake.name()), Some("straight"));
+        assert!(register_snake("straight", || Box::new(StraightSnake)).is_err());
+        assert!(register_snake("greedy", || Box::new(StraightSnake)).is_err());
+        assert_eq!(snake_by_name("greedy", &game).map(|snake| snake.name()), Some("greedy"));
+    }
+
+    #[test]
+    fn heatmap_crosses_out_cells_the_head_cannot_reach() {
+        let mut game = Game::init_with_positions(4, 3, at(0, 0), at(1, 1)).unwrap();
+        for y in 0..3 {
+            game.field.set_direction_at(at(2, y), Direction::Wall);
+        }
+        assert_eq!(game.render().matches('×').count(), 0);
+        game.theme.show_heatmap = true;
+        let frame = game.render();
+        assert_eq!(frame.matches('×').count(), 3);
+        /* the two cells next to the head are one step away */
+        assert_eq!(frame.matches("m1\x1b[0m").count(), 2);
+    }
+
+    #[test]
+    fn legal_directions_toward_put_closing_in_first() {
+        let mut game = Game::init_with_positions(5, 5, at(2, 2), at(0, 0)).unwrap();
+        assert_eq!(game.legal_directions_toward(at(4, 0)), [Direction::Right, Direction::Up, Direction::Left, Direction::Down]);
+        game.field.set_direction_at(at(2, 1), Direction::Wall);
+        assert_eq!(game.legal_directions_toward(at(4, 0)), [Direction::Right, Direction::Left, Direction::Down]);
+        assert_eq!(game.legal_directions_toward(at(0, 4))[..2], [Direction::Left, Direction::Down]);
+    }
+
+    #[test]
+    fn portal_carries_the_head_across_and_the_body_follows() {
+        let mut game = Game::init_with_positions(7, 5, at(1, 1), at(3, 4)).unwrap();
+        assert!(game.add_portal(at(3, 4), at(5, 0)).is_err());
+        game.add_portal(at(2, 1), at(5, 3)).unwrap();
+        game.force_body(&[at(1, 1), at(0, 1), at(0, 2)]);
+        assert_eq!(force_transitions(&mut game, &[Direction::Right]), [StepOutcome::Moved]);
+        assert_eq!(game.head(), at(6, 3));
+        assert_eq!(game.body(), [at(6, 3), at(1, 1), at(0, 1)]);
+        assert_eq!(force_transitions(&mut game, &[Direction::Up, Direction::Left]), [StepOutcome::Moved, StepOutcome::Moved]);
+        assert_eq!(game.body(), [at(5, 2), at(6, 2), at(6, 3)]);
+        assert_eq!(game.field.portal_exit(at(5, 3)), Some(at(2, 1)));
+    }
+
+    #[test]
+    fn move_records_stream_as_one_json_object_per_line() {
+        let mut game = Game::init_with_positions(6, 4, at(1, 1), at(3, 1)).unwrap();
+        game.set_apple_pattern(&[at(3, 1), at(0, 3)]);
+        let mut stream = String::new();
+        for dir in [Direction::Right, Direction::Right, Direction::Down] {
+            let outcome = game.step(dir);
+            stream += &MoveRecord::new(&game, outcome).to_json();
+            stream.push('\n');
+        }
+        let lines = stream.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        for (i, line) in lines.iter().enumerate() {
+            let value:serde_json::Value = serde_json::from_str(line).unwrap();
+            for field in ["moves", "head", "apple", "body", "outcome"] {
+                assert!(value.get(field).is_some(), "{} missing from {}", field, line);
+            }
+            assert_eq!(value["moves"], i + 1);
+        }
+        let eaten:MoveRecord = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!((eaten.head, eaten.apple, eaten.outcome, eaten.body.len()), (at(3, 1), at(0, 3), StepOutcome::AteApple, 2));
+        let last:MoveRecord = serde_json::from_str(lines[2]).unwrap();
+        assert_eq!(last, MoveRecord::new(&game, StepOutcome::Moved));
+    }
+
+    #[test]
+    fn greedy_avoid_self_steers_clear_of_the_body_greedy_runs_into() {
+        let mut game = Game::init_with_positions(6, 6, at(2, 2), at(5, 2)).unwrap();
+        game.force_body(&[at(2, 2), at(2, 1), at(3, 1), at(3, 2), at(3, 3)]);
+        assert_eq!(GreedySnake{}.choose_direction(&game), Some(Direction::Right));
+        assert_eq!(game.collision(Direction::Right), Some(StepOutcome::AteSnake));
+        let dir = GreedyAvoidSelfSnake{}.choose_direction(&game).unwrap();
+        assert!(game.safe_moves().contains(&dir));
+        assert_eq!(force_transitions(&mut game, &[dir]), [StepOutcome::Moved]);
+    }
+
+    #[test]
+    fn origin_shifts_the_drawn_board() {
+        let mut game = Game::init_seeded(5, 4, 2);
+        let plain = game.render();
+        game.theme.cell_width = 2;
+        let wide = game.render();
+        game.theme.origin = (3, 2);
+        let shifted = game.render();
+        let lines = shifted.lines().collect::<Vec<_>>();
+        assert_eq!(lines[..2], ["", ""]);
+        assert_eq!(lines.len(), wide.lines().count() + 2);
+        for (line, unshifted) in lines[2..].iter().zip(wide.lines()) {
+            assert_eq!(*line, format!("      {}", unshifted));
+        }
+        assert_ne!(plain, wide);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn png_export_leaves_a_margin_for_the_origin() {
+        let mut game = Game::init_seeded(7, 5, 3);
+        game.theme.origin = (2, 1);
+        let path = std::env::temp_dir().join(format!("snake-origin-{}.png", std::process::id()));
+        game.write_png(&path, 4).unwrap();
+        let image = image::open(&path).unwrap().to_rgb8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (36, 24));
+        assert_eq!(image.get_pixel(7, 3).0, [0xff, 0xff, 0xff]);
+        let head = game.head();
+        let (x, y) = ((head.x as u32 + 2) * 4, (head.y as u32 + 1) * 4);
+        assert_eq!(image.get_pixel(x, y).0, game.color_at(head));
+    }
+
+    fn hamiltonian_opening(seed:u64, moves:u32) -> Vec<Direction> {
+        let mut game = Game::init_seeded(6, 6, seed);
+        run(&mut game, &mut HamiltonianSnake::new(), &RunOptions{max_moves: Some(moves), ..RunOptions::default()});
+        game.record("hamiltonian").unwrap().directions
+    }
+
+    #[test]
+    fn replay_matches_a_scripted_snake() {
+        let moves = hamiltonian_opening(9, 12);
+        assert_eq!(moves.len(), 12);
+        assert_replay_matches(9, 6, 6, &mut ScriptedSnake::new(&moves), &moves);
+        assert_replay_matches(9, 6, 6, &mut HamiltonianSnake::new(), &moves);
+    }
+
+    #[test]
+    #[should_panic(expected = "went its own way after 5 moves")]
+    fn replay_mismatch_points_at_the_first_difference() {
+        let moves = hamiltonian_opening(9, 12);
+        let mut expected = moves.clone();
+        expected[5] = if moves[5] == Direction::Left { Direction::Right } else { Direction::Left };
+        assert_replay_matches(9, 6, 6, &mut ScriptedSnake::new(&moves), &expected);
+    }
+
+    #[test]
+    fn free_cells_agrees_with_a_scan_of_the_grid() {
+        let scan = |game:&Game| {
+            let cells = (0..game.field.dimension.y).flat_map(|y| (0..game.field.dimension.x).map(move |x| at(x, y)));
+            cells.filter(|pos| game.field.get_direction_at(*pos) == Direction::Null && *pos != game.apple()).count()
+        };
+        let mut game = Game::init_seeded(6, 5, 4);
+        assert_eq!(game.free_cells(), 28);
+        game.generate_walls(0.1);
+        let mut snake = HamiltonianSnake::new();
+        let mut game_with_snake = Game::init_seeded(6, 6, 4);
+        snake.init(&game_with_snake);
+        for _ in 0..60 {
+            assert_eq!(game.free_cells(), scan(&game));
+            assert_eq!(game_with_snake.free_cells(), scan(&game_with_snake));
+            let dir = snake.choose_direction(&game_with_snake).unwrap();
+            game_with_snake.step(dir);
+            if let Some(dir) = game.safe_moves().first() {
+                game.step(*dir);
+            }
+        }
+        assert!(game_with_snake.length() > 1);
+    }
+
+    #[test]
+    fn generated_walls_keep_the_board_connected_at_the_density_asked() {
+        for seed in 0..10 {
+            let mut field = Field::with_size(12, 10).unwrap();
+            let placed = field.generate_walls(&mut StdRng::seed_from_u64(seed), 0.25);
+            assert!(field.is_connected(), "seed {}", seed);
+            assert_eq!(field.wall_count(), placed);
+            assert!((placed as f32 / 120.0 - 0.25).abs() <= 0.05, "{} walls on seed {}", placed, seed);
+        }
+        let mut game = Game::init_seeded(8, 8, 3);
+        game.generate_walls(0.2);
+        assert_eq!(game.cell(game.apple()), Cell::Apple);
+        assert_eq!(game.cell(game.head()), Cell::Head);
+    }
+
+    #[test]
+    fn cell_tells_what_is_on_a_coordinate() {
+        let mut game = Game::init_with_positions(6, 4, at(2, 1), at(5, 3)).unwrap();
+        game.force_body(&[at(2, 1), at(1, 1), at(1, 2)]);
+        game.field.set_direction_at(at(4, 0), Direction::Wall);
+        assert_eq!(game.cell(at(2, 1)), Cell::Head);
+        assert_eq!(game.cell(at(1, 1)), Cell::Body(Direction::Down));
+        assert_eq!(game.cell(at(1, 2)), Cell::Tail);
+        assert_eq!(game.cell(at(5, 3)), Cell::Apple);
+        assert_eq!(game.cell(at(4, 0)), Cell::Wall);
+        assert_eq!(game.cell(at(3, 3)), Cell::Empty);
+        assert_eq!(game.cell(at(6, 0)), Cell::OutOfBounds);
+        assert_eq!(game.cell(at(0, -1)), Cell::OutOfBounds);
+        game.add_portal(at(0, 3), at(3, 0)).unwrap();
+        assert_eq!(game.cell(at(3, 0)), Cell::Portal);
+    }
+
+    #[test]
+    fn eating_the_last_apple_wins_in_the_same_step() {
+        let mut game = Game::init_with_positions(2, 2, at(0, 0), at(1, 0)).unwrap();
+        game.force_body(&[at(0, 0), at(0, 1), at(1, 1)]);
+        assert_eq!(force_transitions(&mut game, &[Direction::Right]), [StepOutcome::Won]);
+        assert_eq!((game.length(), game.free_cells(), game.apples_eaten()), (4, 0, 1));
+        assert_eq!(game.head(), at(1, 0));
+    }
+
+    #[test]
+    fn mock_clock_sleeps_the_delay_once_per_move() {
+        let delay = time::Duration::from_millis(150);
+        let options = RunOptions{draw: true, delay, max_moves: Some(20), ..RunOptions::default()};
+        let mut clock = MockClock::new();
+        let start = time::Instant::now();
+        let result = run_with_clock(&mut Game::init_seeded(6, 6, 2), &mut HamiltonianSnake::new(), &options, &mut clock);
+        assert_eq!(result.moves, 20);
+        assert_eq!(clock.now(), delay * 20);
+        assert!(start.elapsed() < delay * 20);
+    }
+
+    #[test]
+    fn free_neighbor_count_for_corner_edge_and_inside_cells() {
+        let mut game = Game::init_with_positions(5, 5, at(3, 2), at(4, 4)).unwrap();
+        assert_eq!(game.field.free_neighbor_count(at(0, 0)), 2);
+        assert_eq!(game.field.free_neighbor_count(at(2, 0)), 3);
+        assert_eq!(game.field.free_neighbor_count(at(1, 1)), 4);
+        game.field.set_direction_at(at(2, 1), Direction::Wall);
+        /* one side walled off, the head on another */
+        assert_eq!(game.field.free_neighbor_count(at(2, 2)), 2);
+        assert_eq!(game.field.free_neighbor_count(at(4, 4)), 2);
+    }
+
+    #[test]
+    fn viewport_centers_on_the_head_up_to_the_edges() {
+        let mut game = Game::init_with_positions(20, 12, at(10, 6), at(0, 0)).unwrap();
+        assert_eq!(game.viewport(), (at(0, 0), at(20, 12)));
+        game.theme.viewport = Some((5, 3));
+        assert_eq!(game.viewport(), (at(8, 5), at(5, 3)));
+        game.theme.cell_width = 1;
+        assert_eq!(game.render().lines().next(), Some("   89012"));
+        game.head = at(1, 1);
+        assert_eq!(game.viewport(), (at(0, 0), at(5, 3)));
+        game.head = at(19, 11);
+        assert_eq!(game.viewport(), (at(15, 9), at(5, 3)));
+        game.theme.viewport = Some((30, 3));
+        assert_eq!(game.viewport(), (at(0, 9), at(20, 3)));
+    }
+
+    #[test]
+    fn path_efficiency_is_one_straight_on_and_more_for_a_detour() {
+        let play = |moves:&[Direction]| {
+            let mut game = Game::init_with_positions(6, 5, at(0, 2), at(3, 2)).unwrap();
+            assert_eq!(game.path_efficiency(), None);
+            assert_eq!(force_transitions(&mut game, moves).last(), Some(&StepOutcome::AteApple));
+            game.path_efficiency()
+        };
+        assert_eq!(play(&[Direction::Right, Direction::Right, Direction::Right]), Some(1.0));
+        let detour = [Direction::Up, Direction::Right, Direction::Right, Direction::Right, Direction::Down];
+        assert_eq!(play(&detour), Some(5.0 / 3.0));
+    }
+
+    #[test]
+    fn magnet_draws_the_apple_to_an_idle_snake() {
+        let idle = |magnet:Option<u32>| {
+            let mut game = Game::init_with_positions(9, 9, at(0, 0), at(8, 8)).unwrap();
+            game.set_apple_magnet(magnet);
+            let mut distances = Vec::new();
+            for _ in 0..4 {
+                assert_eq!(force_transitions(&mut game, &[Direction::Down, Direction::Up]), [StepOutcome::Moved; 2]);
+                distances.push(game.apple().manhattan_distance(game.head()));
+            }
+            distances
+        };
+        assert_eq!(idle(Some(2)), [15, 14, 13, 12]);
+        assert_eq!(idle(None), [16; 4]);
+    }
+
+    #[test]
+    fn longest_open_run_is_longer_on_an_open_board() {
+        let open = Field::with_size(6, 6).unwrap();
+        let mut cramped = Field::with_size(6, 6).unwrap();
+        for y in 0..6 {
+            cramped.set_direction_at(at(2, y), Direction::Wall);
+        }
+        /* a pocket of two by three cells in the top left corner */
+        cramped.set_direction_at(at(0, 3), Direction::Wall);
+        cramped.set_direction_at(at(1, 3), Direction::Wall);
+        let open_run = open.longest_open_run(at(0, 0));
+        let cramped_run = cramped.longest_open_run(at(0, 0));
+        assert!(open_run > cramped_run, "{} vs {}", open_run, cramped_run);
+        assert!(open_run <= 35);
+        assert!(cramped_run <= 5);
+    }
+
+    #[test]
+    fn lone_head_through_a_portal_onto_itself_eats_the_snake() {
+        let mut game = Game::init_with_positions(5, 3, at(2, 0), at(4, 2)).unwrap();
+        game.add_portal(at(1, 0), at(3, 0)).unwrap();
+        assert_eq!(force_transitions(&mut game, &[Direction::Left]), [StepOutcome::AteSnake]);
+        assert_eq!(game.head(), at(2, 0));
+        assert_eq!(force_transitions(&mut game, &[Direction::Down]), [StepOutcome::Moved]);
+    }
+
+    #[test]
+    fn lone_head_wrapping_around_a_one_wide_board_terminates() {
+        let (done, finished) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for dir in [Direction::Left, Direction::Right] {
+                let mut game = Game::init_with_positions(1, 4, at(0, 1), at(0, 3)).unwrap();
+                game.set_edges(EdgeModes{left: EdgeMode::Wrap, right: EdgeMode::Wrap, top: EdgeMode::Wrap, bottom: EdgeMode::Wrap});
+                let mut outcomes = force_transitions(&mut game, &[dir]);
+                outcomes.extend(force_transitions(&mut game, &[Direction::Up, Direction::Up]));
+                done.send(outcomes).unwrap();
+            }
+        });
+        for _ in 0..2 {
+            let outcomes = finished.recv_timeout(time::Duration::from_secs(5)).expect("step hung");
+            /* up twice goes over the top edge onto the apple */
+            assert_eq!(outcomes, [StepOutcome::AteSnake, StepOutcome::Moved, StepOutcome::AteApple]);
+        }
+    }
+
+    #[test]
+    fn apple_count_scales_with_the_board() {
+        assert_eq!(apples_for_area(50, 50, 25), 100);
+        assert_eq!(apples_for_area(4, 4, 25), 1);
+        assert_eq!(apples_for_area(4, 4, 0), 16);
+        let config = GameConfig{width: 50, height: 50, cells_per_apple: Some(25), ..GameConfig::default()};
+        let game = Game::from_config(&config).unwrap();
+        let apples = game.apple_positions();
+        assert_eq!(apples.len(), 100);
+        assert!(apples.iter().all(|pos| game.cell(*pos) == Cell::Apple));
+        let mut distinct = apples.iter().map(|pos| (pos.x, pos.y)).collect::<Vec<_>>();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 100);
+        assert_eq!(game.free_cells(), 50*50 - 1 - 100);
+        assert!(game.record("greedy").is_err());
+    }
+
+    #[test]
+    fn eaten_extra_apple_grows_the_snake_and_comes_back_elsewhere() {
+        let mut game = Game::init_with_positions(6, 6, at(0, 0), at(5, 5)).unwrap();
+        game.set_debug_invariants(true);
+        game.more_apples.push(at(1, 0));
+        assert_eq!(game.set_apple_count(3), 3);
+        assert_eq!(game.apple_positions()[..2], [at(5, 5), at(1, 0)]);
+        assert_eq!(force_transitions(&mut game, &[Direction::Right]), [StepOutcome::AteApple]);
+        assert_eq!((game.length(), game.apples_eaten(), game.apple_positions().len()), (2, 1, 3));
+        assert!(!game.apple_positions().contains(&at(1, 0)));
+        assert_eq!(game.apple(), at(5, 5));
+
+        /* a board filled up with snake and apples is won once they are all eaten */
+        let mut game = Game::init_with_positions(3, 1, at(0, 0), at(2, 0)).unwrap();
+        game.set_apple_count(2);
+        assert_eq!(game.apple_positions(), [at(2, 0), at(1, 0)]);
+        assert_eq!(force_transitions(&mut game, &[Direction::Right, Direction::Right]), [StepOutcome::AteApple, StepOutcome::Won]);
+    }
+
+    #[test]
+    fn ghost_snake_passes_through_itself() {
+        /* a five long snake heading up, its body off to the right below */
+        let mut game = Game::init_with_positions(6, 6, at(1, 2), at(5, 5)).unwrap();
+        game.set_debug_invariants(true);
+        game.force_body(&[at(1, 2), at(1, 3), at(2, 3), at(3, 3), at(3, 4)]);
+        assert_eq!(game.collision(Direction::Down), Some(StepOutcome::AteSnake));
+        game.set_mode(GameMode::Ghost);
+        /* right, then down through the body and out the other side */
+        let moves = [Direction::Right, Direction::Down, Direction::Down, Direction::Left];
+        assert_eq!(force_transitions(&mut game, &moves), [StepOutcome::Moved; 4]);
+        assert_eq!(game.body(), [at(1, 4), at(2, 4), at(2, 3), at(2, 2), at(1, 2)]);
+        assert_eq!(game.field.buried_count(), 0);
+
+        /* lying over itself, then crawling out again */
+        game.force_body(&[at(2, 2), at(2, 3), at(3, 3), at(3, 2), at(4, 2)]);
+        let moves = [Direction::Down, Direction::Right, Direction::Up, Direction::Up, Direction::Up];
+        let outcomes = force_transitions(&mut game, &moves);
+        assert_eq!(outcomes, [StepOutcome::Moved; 5]);
+        assert_eq!(game.body(), [at(3, 0), at(3, 1), at(3, 2), at(3, 3), at(2, 3)]);
+        assert_eq!(game.length(), 5);
+    }
+
+    #[test]
+    fn ghost_snake_can_eat_while_on_top_of_itself() {
+        let mut game = Game::init_with_positions(5, 5, at(1, 1), at(2, 2)).unwrap();
+        game.set_debug_invariants(true);
+        game.set_mode(GameMode::Ghost);
+        game.force_body(&[at(1, 1), at(2, 1), at(2, 2), at(2, 3)]);
+        game.apple = at(1, 2);
+        /* down onto the apple, then right into the body and along it */
+        let outcomes = force_transitions(&mut game, &[Direction::Down, Direction::Right, Direction::Up]);
+        assert_eq!(outcomes, [StepOutcome::AteApple, StepOutcome::Moved, StepOutcome::Moved]);
+        assert_eq!(game.length(), 5);
+        assert_eq!(game.body().len(), 5);
+        assert_eq!(game.field.buried_count(), 1);
+        assert_eq!(game.free_cells(), 25 - 4 - 1);
+    }
+
+    /* Every look at it finds the clock a millisecond further on */
+    #[derive(Default)]
+    struct TickingClock {
+        now: std::cell::Cell<time::Duration>,
+    }
+    impl Clock for TickingClock {
+        fn now(&self) -> time::Duration {
+            self.now.set(self.now.get() + time::Duration::from_millis(1));
+            self.now.get()
+        }
+        fn sleep(&mut self, duration:time::Duration) {
+            self.now.set(self.now.get() + duration);
+        }
+    }
+
+    #[test]
+    fn decision_and_sweep_timing_go_by_the_clock() {
+        let ms = time::Duration::from_millis;
+        let mut snake = InstrumentedSnake::with_clock(HamiltonianSnake::new(), TickingClock::default());
+        let result = run(&mut Game::init_seeded(4, 4, 3), &mut snake, &RunOptions{max_moves: Some(10), ..RunOptions::default()});
+        assert_eq!(result.moves, 10);
+        assert!(snake.decisions().iter().all(|(took, _)| *took == ms(1)));
+        assert_eq!(snake.total_time(), ms(10));
+
+        let timings = sweep_with_clock(&mut HamiltonianSnake::new(), &[(4, 4), (6, 6)], 50, &mut TickingClock::default());
+        assert_eq!(timings.iter().map(|timing| (timing.moves, timing.elapsed)).collect::<Vec<_>>(), [(50, ms(1)), (50, ms(1))]);
+        assert_eq!(timings[0].moves_per_second(), 50_000.0);
+    }
+
+    #[test]
+    fn max_theoretical_apples_is_zero_on_an_empty_board() {
+        assert_eq!(max_theoretical_apples(0, 0), 0);
+        assert_eq!(max_theoretical_apples(0, 7), 0);
+        assert_eq!(max_theoretical_apples(7, 0), 0);
+        assert_eq!(max_theoretical_apples(1, 1), 0);
+    }
+
+    #[test]
+    fn clamp_onto_an_empty_board_gives_nothing() {
+        assert_eq!(at(2, 1).clamp(at(0, 3)), None);
+        assert_eq!(at(2, 1).clamp(at(5, 0)), None);
+        assert_eq!(at(0, 0).clamp(at(0, 0)), None);
+        assert_eq!(at(-4, 9).clamp(at(1, 1)), Some(at(0, 0)));
+    }
+
+    #[test]
+    fn indented_writes_put_the_indent_in_front_of_every_line() {
+        use std::io::Write;
+        let mut out = Vec::new();
+        let mut indented = Indented{out:&mut out, indent:b"..", line_start:true};
+        for piece in ["ab", "c\nd", "e\n", "\n", "f\n"] {
+            indented.write_all(piece.as_bytes()).unwrap();
+        }
+        assert_eq!(String::from_utf8(out).unwrap(), "..abc\n..de\n..\n..f\n");
+    }
+
+    #[test]
+    fn connectivity_snake_looks_ahead_on_the_field_as_the_game_would_step() {
+        for (seed, mode) in [(1, GameMode::Classic), (2, GameMode::Classic), (3, GameMode::Ghost), (4, GameMode::Ghost)] {
+            let mut game = Game::init_seeded(6, 5, seed);
+            game.set_mode(mode);
+            let mut rng = StdRng::seed_from_u64(seed);
+            for _ in 0..200 {
+                let moves = game.safe_moves();
+                let Some(&dir) = moves.choose(&mut rng) else { break };
+                for candidate in moves {
+                    let (field, length) = ConnectivitySnake::field_after(&game, candidate);
+                    let mut after = game.clone();
+                    if after.step(candidate) == StepOutcome::Won {
+                        continue;
+                    }
+                    assert_eq!(field.free_cells().collect::<Vec<_>>(), after.field.free_cells().collect::<Vec<_>>());
+                    assert_eq!(field.buried_count(), after.field.buried_count());
+                    assert_eq!(length, after.length);
+                }
+                if game.step(dir) == StepOutcome::Won {
+                    break;
+                }
+            }
+        }
+    }
+}